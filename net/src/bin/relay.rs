@@ -0,0 +1,11 @@
+//! Runs the relay online players connect through, `relay [address]`
+
+fn main() {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:9001".to_string());
+    if let Err(e) = asteroids_net::relay::run(&addr) {
+        eprintln!("relay: {e}");
+        std::process::exit(1);
+    }
+}
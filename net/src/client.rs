@@ -0,0 +1,49 @@
+use std::{io, net::TcpStream};
+
+use tungstenite::{stream::MaybeTlsStream, Error, WebSocket};
+
+use crate::Message;
+
+/// Non-blocking connection to the relay for native builds
+pub struct Client {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+}
+
+impl Client {
+    /// Connects to a relay url such as `ws://127.0.0.1:9001`, blocking until the handshake is done
+    pub fn connect(url: &str) -> Result<Self, Box<Error>> {
+        let (socket, _) = tungstenite::connect(url)?;
+        if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+            stream.set_nodelay(true).map_err(Error::Io)?;
+            stream.set_nonblocking(true).map_err(Error::Io)?;
+        }
+        Ok(Self { socket })
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), Box<Error>> {
+        // frames that can't be written yet stay queued until the next flush
+        match self.socket.send(message.encode().into()) {
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            result => Ok(result?),
+        }
+    }
+
+    /// Every message that has arrived since the last call
+    pub fn receive(&mut self) -> Result<Vec<Message>, Box<Error>> {
+        let mut messages = Vec::new();
+        loop {
+            match self.socket.read() {
+                Ok(tungstenite::Message::Binary(bytes)) => {
+                    messages.extend(Message::decode(&bytes));
+                }
+                Ok(_) => {}
+                Err(Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        match self.socket.flush() {
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => Ok(messages),
+            result => Ok(result.map(|_| messages)?),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+//! Networking shared by the game and the relay server that connects online players
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod client;
+pub mod protocol;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod relay;
+
+pub use protocol::Message;
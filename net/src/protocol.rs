@@ -0,0 +1,141 @@
+/// Messages passed between the game and the relay, sent as binary WebSocket frames
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Asks to play in the named room once it has the given number of players
+    Join { room: String, players: u8 },
+    /// Tells a client which player it controls
+    Welcome { player: u8 },
+    /// The room is full, everyone starts from the same seed
+    Start { seed: u64, players: u8 },
+    /// A player's inputs for the ticks starting at `tick`, along with the first tick the sender
+    /// is still missing from the others so they know what to send again. The relay fills in
+    /// the sender
+    Input {
+        player: u8,
+        ack: u32,
+        tick: u32,
+        inputs: Vec<u8>,
+    },
+    /// Checksum of the sender's game at the start of a tick, for spotting desyncs
+    Checksum {
+        player: u8,
+        tick: u32,
+        checksum: u64,
+    },
+    /// A player disconnected
+    PeerLeft { player: u8 },
+}
+
+const JOIN: u8 = 0;
+const WELCOME: u8 = 1;
+const START: u8 = 2;
+const INPUT: u8 = 3;
+const PEER_LEFT: u8 = 4;
+const CHECKSUM: u8 = 5;
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Join { room, players } => [&[JOIN, *players], room.as_bytes()].concat(),
+            Self::Welcome { player } => vec![WELCOME, *player],
+            Self::Start { seed, players } => [&[START, *players], &seed.to_le_bytes()[..]].concat(),
+            Self::Input {
+                player,
+                ack,
+                tick,
+                inputs,
+            } => [
+                &[INPUT, *player],
+                &ack.to_le_bytes()[..],
+                &tick.to_le_bytes()[..],
+                inputs,
+            ]
+            .concat(),
+            Self::Checksum {
+                player,
+                tick,
+                checksum,
+            } => [
+                &[CHECKSUM, *player],
+                &tick.to_le_bytes()[..],
+                &checksum.to_le_bytes()[..],
+            ]
+            .concat(),
+            Self::PeerLeft { player } => vec![PEER_LEFT, *player],
+        }
+    }
+
+    /// Reads a message, none if it is malformed
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        match *bytes {
+            [JOIN, players, ref room @ ..] => Some(Self::Join {
+                room: String::from_utf8(room.to_vec()).ok()?,
+                players,
+            }),
+            [WELCOME, player] => Some(Self::Welcome { player }),
+            [START, players, ref seed @ ..] => Some(Self::Start {
+                seed: u64::from_le_bytes(seed.try_into().ok()?),
+                players,
+            }),
+            [INPUT, player, a0, a1, a2, a3, t0, t1, t2, t3, ref inputs @ ..] => Some(Self::Input {
+                player,
+                ack: u32::from_le_bytes([a0, a1, a2, a3]),
+                tick: u32::from_le_bytes([t0, t1, t2, t3]),
+                inputs: inputs.to_vec(),
+            }),
+            [CHECKSUM, player, t0, t1, t2, t3, ref checksum @ ..] => Some(Self::Checksum {
+                player,
+                tick: u32::from_le_bytes([t0, t1, t2, t3]),
+                checksum: u64::from_le_bytes(checksum.try_into().ok()?),
+            }),
+            [PEER_LEFT, player] => Some(Self::PeerLeft { player }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_survive_a_round_trip() {
+        let messages = [
+            Message::Join {
+                room: "versus".to_string(),
+                players: 2,
+            },
+            Message::Welcome { player: 1 },
+            Message::Start {
+                seed: 0x0123_4567_89ab_cdef,
+                players: 2,
+            },
+            Message::Input {
+                player: 1,
+                ack: 69_990,
+                tick: 70_000,
+                inputs: vec![0b10_1010, 0, 0b1],
+            },
+            Message::Checksum {
+                player: 0,
+                tick: 120,
+                checksum: u64::MAX - 7,
+            },
+            Message::PeerLeft { player: 0 },
+        ];
+
+        for message in messages {
+            assert_eq!(Message::decode(&message.encode()), Some(message));
+        }
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected() {
+        assert_eq!(Message::decode(&[]), None);
+        assert_eq!(Message::decode(&[START, 2, 1, 2, 3]), None);
+        assert_eq!(Message::decode(&[INPUT, 0, 0]), None);
+        assert_eq!(Message::decode(&[CHECKSUM, 0, 1, 0, 0, 0, 9]), None);
+        assert_eq!(Message::decode(&[JOIN, 2, 0xff]), None);
+        assert_eq!(Message::decode(&[99]), None);
+    }
+}
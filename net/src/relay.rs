@@ -0,0 +1,201 @@
+//! Relay server pairing players up into rooms and forwarding their inputs to each other
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    error::Error,
+    hash::{BuildHasher, Hasher},
+    io,
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use tungstenite::WebSocket;
+
+use crate::Message;
+
+// how long a connection waits for its client before passing on messages from the other peers
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+struct Room {
+    size: u8,
+    started: bool,
+    peers: Vec<Option<Sender<Message>>>,
+}
+
+impl Room {
+    fn broadcast(&self, message: &Message, except: Option<usize>) {
+        self.peers
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| Some(i) != except)
+            .filter_map(|(_, peer)| peer.as_ref())
+            .for_each(|peer| {
+                // a peer that has gone is cleaned up by its own connection
+                let _ = peer.send(message.clone());
+            });
+    }
+}
+
+/// Rooms still waiting for players, by name and size
+type Lobby = Arc<Mutex<HashMap<(String, u8), Arc<Mutex<Room>>>>>;
+
+/// Accepts connections on the address forever, each one handled on its own thread
+pub fn run(addr: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("relay listening on {}", listener.local_addr()?);
+    serve(listener)
+}
+
+/// Relays for clients connecting to an already bound listener
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let lobby = Lobby::default();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("relay: failed to accept connection: {e}");
+                continue;
+            }
+        };
+        let lobby = lobby.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, &lobby) {
+                eprintln!("relay: connection closed: {e}");
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle(stream: TcpStream, lobby: &Lobby) -> Result<(), Box<dyn Error>> {
+    stream.set_nodelay(true)?;
+    let mut socket = tungstenite::accept(stream)?;
+
+    // the first message picks the room, anything else is a confused client
+    let (name, size) = match socket.read()? {
+        tungstenite::Message::Binary(bytes) => match Message::decode(&bytes) {
+            Some(Message::Join { room, players }) if players > 0 => (room, players),
+            _ => return Err("expected a join message".into()),
+        },
+        _ => return Err("expected a binary message".into()),
+    };
+    socket.get_mut().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (sender, receiver) = mpsc::channel();
+    let (room, player) = join(lobby, name, size, sender);
+    let result = forward(&mut socket, &room, player, &receiver);
+    leave(lobby, &room, player);
+    result
+}
+
+/// Adds the player to a waiting room, starting the game once it is full
+fn join(
+    lobby: &Lobby,
+    name: String,
+    size: u8,
+    sender: Sender<Message>,
+) -> (Arc<Mutex<Room>>, usize) {
+    let mut lobby = lobby.lock().unwrap();
+    let key = (name, size);
+    let room = lobby
+        .entry(key.clone())
+        .or_insert_with(|| {
+            Arc::new(Mutex::new(Room {
+                size,
+                started: false,
+                peers: Vec::new(),
+            }))
+        })
+        .clone();
+
+    let mut waiting = room.lock().unwrap();
+    let player = waiting.peers.len();
+    let _ = sender.send(Message::Welcome {
+        player: player as u8,
+    });
+    waiting.peers.push(Some(sender));
+
+    if waiting.peers.len() == waiting.size as usize {
+        waiting.started = true;
+        lobby.remove(&key);
+        let seed = RandomState::new().build_hasher().finish();
+        waiting.broadcast(
+            &Message::Start {
+                seed,
+                players: waiting.size,
+            },
+            None,
+        );
+    }
+    drop(waiting);
+
+    (room, player)
+}
+
+/// Passes messages between the client and the rest of its room until it disconnects
+fn forward(
+    socket: &mut WebSocket<TcpStream>,
+    room: &Mutex<Room>,
+    player: usize,
+    receiver: &Receiver<Message>,
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        for message in receiver.try_iter() {
+            socket.send(message.encode().into())?;
+        }
+
+        match socket.read() {
+            Ok(tungstenite::Message::Binary(bytes)) => {
+                // messages are stamped with the sender so clients can't speak for each other
+                let message = match Message::decode(&bytes) {
+                    Some(Message::Input {
+                        ack, tick, inputs, ..
+                    }) => Message::Input {
+                        player: player as u8,
+                        ack,
+                        tick,
+                        inputs,
+                    },
+                    Some(Message::Checksum { tick, checksum, .. }) => Message::Checksum {
+                        player: player as u8,
+                        tick,
+                        checksum,
+                    },
+                    _ => continue,
+                };
+                room.lock().unwrap().broadcast(&message, Some(player));
+            }
+            Ok(tungstenite::Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn leave(lobby: &Lobby, room: &Arc<Mutex<Room>>, player: usize) {
+    let mut lobby = lobby.lock().unwrap();
+    let mut left = room.lock().unwrap();
+    left.peers[player] = None;
+    left.broadcast(
+        &Message::PeerLeft {
+            player: player as u8,
+        },
+        None,
+    );
+
+    // a room that lost a player before starting can never fill up
+    if !left.started {
+        lobby.retain(|_, waiting| !Arc::ptr_eq(waiting, room));
+    }
+}
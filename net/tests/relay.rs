@@ -0,0 +1,63 @@
+use std::{net::TcpListener, thread, time::Duration};
+
+use asteroids_net::{client::Client, relay, Message};
+
+/// Polls the client until it has received the given number of messages
+fn receive(client: &mut Client, count: usize) -> Vec<Message> {
+    let mut messages = Vec::new();
+    for _ in 0..500 {
+        messages.extend(client.receive().unwrap());
+        if messages.len() >= count {
+            break;
+        }
+        thread::sleep(Duration::from_millis(2));
+    }
+    messages
+}
+
+#[test]
+fn two_clients_are_paired_and_swap_inputs() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    thread::spawn(move || relay::serve(listener));
+
+    let join = Message::Join {
+        room: "coop".to_string(),
+        players: 2,
+    };
+    let mut first = Client::connect(&url).unwrap();
+    first.send(&join).unwrap();
+    assert_eq!(receive(&mut first, 1), [Message::Welcome { player: 0 }]);
+
+    let mut second = Client::connect(&url).unwrap();
+    second.send(&join).unwrap();
+    let second_messages = receive(&mut second, 2);
+    let first_messages = receive(&mut first, 1);
+    assert_eq!(second_messages[0], Message::Welcome { player: 1 });
+    let Message::Start { seed, players: 2 } = second_messages[1] else {
+        panic!("expected the game to start, got {second_messages:?}");
+    };
+    assert_eq!(first_messages, [Message::Start { seed, players: 2 }]);
+
+    // the relay stamps the real sender over whatever the client claimed
+    second
+        .send(&Message::Input {
+            player: 0,
+            ack: 3,
+            tick: 7,
+            inputs: vec![0b1001],
+        })
+        .unwrap();
+    assert_eq!(
+        receive(&mut first, 1),
+        [Message::Input {
+            player: 1,
+            ack: 3,
+            tick: 7,
+            inputs: vec![0b1001],
+        }]
+    );
+
+    drop(second);
+    assert_eq!(receive(&mut first, 1), [Message::PeerLeft { player: 1 }]);
+}
@@ -0,0 +1,323 @@
+//! Achievements unlocked from what happens in play, kept between runs
+
+use macroquad::{prelude::*, ui::root_ui};
+
+use crate::{
+    events::GameEvent,
+    render::{Frame, Renderer, Screen},
+    screen_bounds, storage, Kill, SaucerSize, ACHIEVEMENT_COLOR, ACHIEVEMENT_TOAST_TIME,
+    BACKGROUND_COLOR,
+};
+
+const STORAGE_KEY: &str = "achievements";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    Untouchable,
+    Sharpshooter,
+    SteadyNerves,
+    Century,
+    Survivor,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 5] = [
+        Self::Untouchable,
+        Self::Sharpshooter,
+        Self::SteadyNerves,
+        Self::Century,
+        Self::Survivor,
+    ];
+
+    /// Name it is saved under
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Untouchable => "untouchable",
+            Self::Sharpshooter => "sharpshooter",
+            Self::SteadyNerves => "steady_nerves",
+            Self::Century => "century",
+            Self::Survivor => "survivor",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Untouchable => "Untouchable",
+            Self::Sharpshooter => "Sharpshooter",
+            Self::SteadyNerves => "Steady Nerves",
+            Self::Century => "Century",
+            Self::Survivor => "Survivor",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Untouchable => "Clear wave 5 without losing a ship",
+            Self::Sharpshooter => "Destroy a small saucer with a single shot",
+            Self::SteadyNerves => "Clear 3 waves in a row without using hyperspace",
+            Self::Century => "Destroy 100 asteroids in one game",
+            Self::Survivor => "Reach wave 10",
+        }
+    }
+
+    /// Progress needed to unlock it
+    pub fn goal(&self) -> usize {
+        match self {
+            Self::Untouchable => 5,
+            Self::Sharpshooter => 1,
+            Self::SteadyNerves => 3,
+            Self::Century => 100,
+            Self::Survivor => 10,
+        }
+    }
+}
+
+pub struct Achievements {
+    // best progress ever made towards each, it is unlocked once that reaches the goal
+    best: [usize; Achievement::ALL.len()],
+    // progress in the game being played
+    current: [usize; Achievement::ALL.len()],
+    // a ship has been lost this game
+    died: bool,
+    // newly unlocked achievements and how long they have been shown for
+    toasts: Vec<(Achievement, f32)>,
+}
+
+impl Achievements {
+    /// Reads back what has been saved, anything unrecognised is ignored
+    pub fn parse(saved: &str) -> Self {
+        let mut best = [0; Achievement::ALL.len()];
+        for line in saved.lines() {
+            let mut words = line.split_whitespace();
+            if let (Some(key), Some(Ok(progress))) = (words.next(), words.next().map(str::parse)) {
+                if let Some(i) = Achievement::ALL.iter().position(|a| a.key() == key) {
+                    best[i] = progress;
+                }
+            }
+        }
+        Self {
+            best,
+            current: [0; Achievement::ALL.len()],
+            died: false,
+            toasts: Vec::new(),
+        }
+    }
+
+    pub fn load() -> Self {
+        Self::parse(&storage::load(STORAGE_KEY).unwrap_or_default())
+    }
+
+    /// One line per achievement, the progress made towards it after its name
+    fn serialise(&self) -> String {
+        Achievement::ALL
+            .iter()
+            .zip(self.best)
+            .map(|(a, progress)| format!("{} {progress}\n", a.key()))
+            .collect()
+    }
+
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, &self.serialise());
+    }
+
+    pub fn progress(&self, achievement: Achievement) -> usize {
+        self.best[achievement as usize].min(achievement.goal())
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.best[achievement as usize] >= achievement.goal()
+    }
+
+    /// Tracks progress from what happened in play, saying whether there is anything worth saving
+    pub fn handle(&mut self, events: &[GameEvent]) -> bool {
+        let mut changed = false;
+        for event in events {
+            match *event {
+                // the first wave starting is a new game
+                GameEvent::WaveStarted { wave: 1 } => {
+                    self.current = [0; Achievement::ALL.len()];
+                    self.died = false;
+                }
+                GameEvent::WaveStarted { wave } => {
+                    self.current[Achievement::SteadyNerves as usize] += 1;
+                    self.current[Achievement::Survivor as usize] = wave;
+                    if !self.died {
+                        self.current[Achievement::Untouchable as usize] = wave - 1;
+                    }
+                }
+                GameEvent::ShipHit { .. } => self.died = true,
+                GameEvent::HyperspaceUsed { .. } => {
+                    self.current[Achievement::SteadyNerves as usize] = 0
+                }
+                GameEvent::AsteroidDestroyed { by: Some(_), .. } => {
+                    self.current[Achievement::Century as usize] += 1
+                }
+                // the bullet that brought it down was the only one its player had in the air
+                GameEvent::SaucerDestroyed {
+                    size: SaucerSize::Small,
+                    kill: Some(Kill::Shot { shots: 1 }),
+                    ..
+                } => self.current[Achievement::Sharpshooter as usize] = 1,
+                // progress is saved once the game is over rather than all the way through
+                GameEvent::GameOver => changed = true,
+                _ => (),
+            }
+
+            for achievement in Achievement::ALL {
+                let i = achievement as usize;
+                if !self.is_unlocked(achievement) && self.current[i] >= achievement.goal() {
+                    self.toasts.push((achievement, 0.0));
+                    changed = true;
+                }
+                self.best[i] = self.best[i].max(self.current[i]);
+            }
+        }
+        changed
+    }
+
+    pub fn update_toasts(&mut self, delta_t: f32) {
+        // one at a time, in the order they were unlocked
+        if let Some((_, time)) = self.toasts.first_mut() {
+            *time += delta_t;
+        }
+        self.toasts
+            .retain(|(_, time)| *time < ACHIEVEMENT_TOAST_TIME);
+    }
+
+    /// Announces the earliest unlock not yet shown off, sliding in from the top of the screen
+    pub fn toast_frame(&self, screen: Vec2) -> Frame {
+        let mut frame = Frame::default();
+        let Some((achievement, time)) = self.toasts.first() else {
+            return frame;
+        };
+        let slide = (time.min(ACHIEVEMENT_TOAST_TIME - time) * 4.0).min(1.0);
+        let (width, height) = (360.0, 56.0);
+        let x = screen.x / 2.0 - width / 2.0;
+        let y = -height + (height + 12.0) * slide;
+
+        frame.rectangle(x, y, width, height, BACKGROUND_COLOR);
+        frame.rectangle_lines(x, y, width, height, 2.0, ACHIEVEMENT_COLOR);
+        frame.centered_text(
+            &format!("Achievement unlocked: {}", achievement.title()),
+            Vec2::new(screen.x / 2.0, y + 20.0),
+            22.0,
+            ACHIEVEMENT_COLOR,
+        );
+        frame.centered_text(
+            achievement.description(),
+            Vec2::new(screen.x / 2.0, y + 40.0),
+            16.0,
+            WHITE,
+        );
+        frame
+    }
+
+    /// Every achievement with a bar showing how close it is to being unlocked
+    pub fn screen_frame(&self, screen: Vec2) -> Frame {
+        let mut frame = Frame::default();
+        frame.rectangle(0.0, 0.0, screen.x, screen.y, BACKGROUND_COLOR);
+        frame.centered_text("Achievements", Vec2::new(screen.x / 2.0, 80.0), 48.0, WHITE);
+
+        let (width, x) = (420.0, screen.x / 2.0 - 210.0);
+        for (i, achievement) in Achievement::ALL.into_iter().enumerate() {
+            let y = 140.0 + i as f32 * 70.0;
+            let unlocked = self.is_unlocked(achievement);
+            let color = if unlocked { ACHIEVEMENT_COLOR } else { GRAY };
+            frame.text(achievement.title(), Vec2::new(x, y), 28.0, color);
+            frame.text(
+                achievement.description(),
+                Vec2::new(x, y + 20.0),
+                18.0,
+                LIGHTGRAY,
+            );
+
+            let fill = self.progress(achievement) as f32 / achievement.goal() as f32;
+            frame.rectangle_lines(x, y + 28.0, width, 8.0, 1.0, color);
+            frame.rectangle(x, y + 28.0, width * fill, 8.0, color);
+            let label = format!("{}/{}", self.progress(achievement), achievement.goal());
+            frame.text(&label, Vec2::new(x + width + 10.0, y + 36.0), 18.0, color);
+        }
+        frame
+    }
+
+    /// The achievements screen and its way back, saying whether the player has finished looking
+    pub fn draw_screen(&self) -> bool {
+        Screen.render(&self.screen_frame(screen_bounds()));
+        root_ui().button(
+            Vec2::new(screen_width() / 2.0 - 20.0, screen_height() - 60.0),
+            "Back",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AsteroidType, DeathCause};
+
+    fn waves(from: usize, to: usize) -> Vec<GameEvent> {
+        (from..=to)
+            .map(|wave| GameEvent::WaveStarted { wave })
+            .collect()
+    }
+
+    #[test]
+    fn progress_unlocks_and_survives_a_save() {
+        let mut achievements = Achievements::parse("");
+        let destroyed = GameEvent::AsteroidDestroyed {
+            size: AsteroidType::Small,
+            by: Some(0),
+            pos: Vec2::ZERO,
+        };
+        assert!(!achievements.handle(&[&waves(1, 1)[..], &[destroyed; 60]].concat()));
+        assert!(achievements.handle(&[GameEvent::GameOver]));
+
+        // a new game starts counting again, but the best so far is kept
+        achievements.handle(&[&waves(1, 1)[..], &[destroyed; 40]].concat());
+        assert_eq!(achievements.progress(Achievement::Century), 60);
+        assert!(achievements.handle(&[destroyed; 60]));
+        assert!(achievements.is_unlocked(Achievement::Century));
+        assert_eq!(achievements.toasts.len(), 1);
+
+        let loaded = Achievements::parse(&achievements.serialise());
+        assert!(loaded.is_unlocked(Achievement::Century));
+        assert!(!loaded.is_unlocked(Achievement::Survivor));
+    }
+
+    #[test]
+    fn dying_or_jumping_resets_the_streaks() {
+        let mut achievements = Achievements::parse("");
+        let hyperspace = GameEvent::HyperspaceUsed {
+            player: 0,
+            pos: Vec2::ZERO,
+        };
+        achievements.handle(&[&waves(1, 3)[..], &[hyperspace], &waves(4, 5)].concat());
+        assert_eq!(achievements.progress(Achievement::SteadyNerves), 2);
+        assert_eq!(achievements.progress(Achievement::Untouchable), 4);
+
+        let hit = GameEvent::ShipHit {
+            player: 0,
+            cause: DeathCause::SaucerBullet,
+            pos: Vec2::ZERO,
+        };
+        achievements.handle(&[&[hit][..], &waves(6, 8)].concat());
+        assert!(achievements.is_unlocked(Achievement::SteadyNerves));
+        assert!(!achievements.is_unlocked(Achievement::Untouchable));
+    }
+
+    #[test]
+    fn only_a_first_shot_kill_is_sharpshooting() {
+        let mut achievements = Achievements::parse("");
+        let destroyed = |kill| GameEvent::SaucerDestroyed {
+            size: SaucerSize::Small,
+            by: Some(0),
+            kill: Some(kill),
+            pos: Vec2::ZERO,
+        };
+
+        achievements.handle(&[destroyed(Kill::Shot { shots: 2 }), destroyed(Kill::Rammed)]);
+        assert!(!achievements.is_unlocked(Achievement::Sharpshooter));
+        achievements.handle(&[destroyed(Kill::Shot { shots: 1 })]);
+        assert!(achievements.is_unlocked(Achievement::Sharpshooter));
+    }
+}
@@ -0,0 +1,315 @@
+//! Plays the synthesised sound effects in response to what happens in play, placed in stereo
+//! where it happens
+
+use macroquad::{
+    audio::{
+        load_sound_from_bytes, play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound,
+    },
+    prelude::*,
+};
+
+use crate::{
+    events::GameEvent,
+    mixer::{mix, Voice},
+    settings::Settings,
+    synth::{wav, Sfx},
+    AsteroidType, Game, SaucerSize, DOPPLER_SHIFT, MUSIC_FASTEST_BEAT, MUSIC_SLOWEST_BEAT,
+    MUSIC_THINNING, MUSIC_WAVE_SPEEDUP, STEREO_EDGE_ATTENUATION, STEREO_PAN_STEPS, STEREO_WIDTH,
+};
+
+/// Seconds between heartbeats, quickening through each wave as its asteroids thin out, and a
+/// little more with every wave
+pub fn beat_interval(game: &Game) -> f32 {
+    let spawned = game.asteroids_spawned_in_wave as f32 / game.get_wave_asteroid_amount() as f32;
+    // one with none left, falling away the more there are
+    let thinned = MUSIC_THINNING / (MUSIC_THINNING + game.asteroids.len() as f32);
+    let urgency = spawned * (0.5 + 0.5 * thinned) + game.asteroid_wave as f32 * MUSIC_WAVE_SPEEDUP;
+    MUSIC_SLOWEST_BEAT + (MUSIC_FASTEST_BEAT - MUSIC_SLOWEST_BEAT) * urgency.min(1.0)
+}
+
+/// Pans a sound towards the side of the playfield it came from, a little quieter the further out
+pub fn placement(pos: Vec2, bounds: Vec2) -> (f32, f32) {
+    let pan = ((pos.x / bounds.x).clamp(0.0, 1.0) * 2.0 - 1.0) * STEREO_WIDTH;
+    (pan, 1.0 - STEREO_EDGE_ATTENUATION * pan.abs())
+}
+
+/// Playback rate for a saucer's siren, higher as it closes on the middle of the playfield and
+/// lower as it flies away, evening out as it passes
+pub fn doppler(pos: Vec2, vel: Vec2, bounds: Vec2) -> f32 {
+    let to_middle = bounds.x / 2.0 - pos.x;
+    let closing = vel.x * to_middle.signum() / vel.length().max(f32::EPSILON);
+    let distance = (to_middle.abs() / (bounds.x / 4.0)).min(1.0);
+    1.0 + DOPPLER_SHIFT * closing * distance
+}
+
+/// Index of the rendered pan position closest to the pan
+fn pan_step(pan: f32) -> usize {
+    ((pan.clamp(-1.0, 1.0) + 1.0) / 2.0 * (STEREO_PAN_STEPS - 1) as f32).round() as usize
+}
+
+fn step_pan(step: usize) -> f32 {
+    step as f32 / (STEREO_PAN_STEPS - 1) as f32 * 2.0 - 1.0
+}
+
+/// Playback rates saucer sirens are rendered at, slowest first
+const DOPPLER_RATES: [f32; 3] = [1.0 - DOPPLER_SHIFT, 1.0, 1.0 + DOPPLER_SHIFT];
+
+fn pitch_step(rate: f32) -> usize {
+    (0..DOPPLER_RATES.len())
+        .min_by(|a, b| {
+            (DOPPLER_RATES[*a] - rate)
+                .abs()
+                .total_cmp(&(DOPPLER_RATES[*b] - rate).abs())
+        })
+        .unwrap_or(1)
+}
+
+/// One of the versions each sound is rendered in, as macroquad can only play sounds back as they
+/// are with a volume
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Variant {
+    sfx: Sfx,
+    pan: usize,
+    pitch: usize,
+}
+
+impl Variant {
+    /// Every version of the sound worth having, only the saucer sirens change pitch and only
+    /// sounds from something on the playfield are panned
+    fn all(sfx: Sfx) -> Vec<Self> {
+        let pans = match sfx {
+            Sfx::ExtraLife | Sfx::BeatLow | Sfx::BeatHigh => {
+                STEREO_PAN_STEPS / 2..=STEREO_PAN_STEPS / 2
+            }
+            _ => 0..=STEREO_PAN_STEPS - 1,
+        };
+        let pitches = match sfx {
+            Sfx::SaucerWarble(_) => 0..=DOPPLER_RATES.len() - 1,
+            _ => 1..=1,
+        };
+        pans.flat_map(|pan| pitches.clone().map(move |pitch| Self { sfx, pan, pitch }))
+            .collect()
+    }
+
+    fn render(&self, samples: &[f32]) -> Vec<u8> {
+        let voice = Voice {
+            pan: step_pan(self.pan),
+            rate: DOPPLER_RATES[self.pitch],
+            ..Voice::new(samples)
+        };
+        wav(&mix(&[voice]), 2)
+    }
+}
+
+pub struct Audio {
+    sounds: Vec<(Variant, Sound)>,
+    // looped sounds currently playing and their gain
+    playing: Vec<(Variant, f32)>,
+    settings: Settings,
+    // time since the last heartbeat, and whether the next is the high note
+    since_beat: f32,
+    high_beat: bool,
+}
+
+impl Audio {
+    /// Renders every sound up front, any that can't be loaded stay silent
+    pub async fn load(settings: Settings) -> Self {
+        let mut sounds = Vec::new();
+        for sfx in Sfx::ALL {
+            let samples = sfx.render();
+            for variant in Variant::all(sfx) {
+                match load_sound_from_bytes(&variant.render(&samples)).await {
+                    Ok(sound) => sounds.push((variant, sound)),
+                    Err(e) => eprintln!("can't load the {sfx:?} sound: {e}"),
+                }
+            }
+        }
+        Self {
+            sounds,
+            playing: Vec::new(),
+            settings,
+            since_beat: 0.0,
+            high_beat: false,
+        }
+    }
+
+    /// The version of the sound for where it comes from, centred without a position
+    fn variant(sfx: Sfx, pos: Option<Vec2>, bounds: Vec2, rate: f32) -> (Variant, f32) {
+        let (pan, gain) = pos.map_or((0.0, 1.0), |pos| placement(pos, bounds));
+        let variant = Variant {
+            sfx,
+            pan: pan_step(pan),
+            pitch: pitch_step(rate),
+        };
+        // fall back to the centre for sounds that aren't panned
+        if Variant::all(sfx).contains(&variant) {
+            (variant, gain)
+        } else {
+            (Variant::all(sfx)[0], 1.0)
+        }
+    }
+
+    fn sound(&self, variant: Variant) -> Option<&Sound> {
+        self.sounds
+            .iter()
+            .find(|(v, _)| *v == variant)
+            .map(|(_, sound)| sound)
+    }
+
+    fn volume(&self, sfx: Sfx, gain: f32) -> f32 {
+        let channel = if sfx.is_music() {
+            self.settings.music_volume
+        } else {
+            self.settings.sfx_volume
+        };
+        sfx.volume() * gain * channel * self.settings.master_volume
+    }
+
+    fn play(&self, (variant, gain): (Variant, f32)) {
+        if let Some(sound) = self.sound(variant) {
+            play_sound(
+                sound,
+                PlaySoundParams {
+                    looped: variant.sfx.is_looped(),
+                    volume: self.volume(variant.sfx, gain),
+                },
+            );
+        }
+    }
+
+    /// Picks up changes from the mixer, including for sounds already playing
+    pub fn set_volumes(&mut self, settings: Settings) {
+        self.settings = settings;
+        for &(variant, gain) in &self.playing {
+            if let Some(sound) = self.sound(variant) {
+                set_sound_volume(sound, self.volume(variant.sfx, gain));
+            }
+        }
+    }
+
+    /// One shot sounds for what just happened, placed where it happened on the playfield
+    pub fn handle(&self, events: &[GameEvent], bounds: Vec2) {
+        for event in events {
+            let sfx = match *event {
+                GameEvent::BulletFired { .. } => Sfx::Laser,
+                GameEvent::AsteroidDestroyed { size, .. } => Sfx::Explosion(size),
+                GameEvent::SaucerDestroyed { .. } => Sfx::Explosion(AsteroidType::Medium),
+                GameEvent::ShipHit { .. } => Sfx::Explosion(AsteroidType::Large),
+                GameEvent::HyperspaceUsed { .. } => Sfx::Hyperspace,
+                GameEvent::ExtraLife { .. } => Sfx::ExtraLife,
+                _ => continue,
+            };
+            self.play(Self::variant(sfx, event.pos(), bounds, 1.0));
+        }
+    }
+
+    /// Keeps the heartbeat and the looped sounds going for as long as something is making them,
+    /// following whatever makes them around the playfield, silent with no game
+    pub fn update(&mut self, game: Option<&Game>, delta_t: f32) {
+        let mut wanted = Vec::new();
+        if let Some(game) = game.filter(|game| !game.game_over) {
+            self.since_beat += delta_t;
+            if self.since_beat >= beat_interval(game) {
+                self.since_beat = 0.0;
+                let beat = if self.high_beat {
+                    Sfx::BeatHigh
+                } else {
+                    Sfx::BeatLow
+                };
+                self.play(Self::variant(beat, None, game.bounds, 1.0));
+                self.high_beat = !self.high_beat;
+            }
+
+            if let Some(player) = game.players.iter().find(|p| p.ship.thrusting) {
+                let pos = Some(player.ship.pos);
+                wanted.push(Self::variant(Sfx::Thrust, pos, game.bounds, 1.0));
+            }
+            // the small saucer's siren drowns out the large one's
+            if let Some(saucer) = game
+                .saucers
+                .iter()
+                .max_by_key(|s| s.size == SaucerSize::Small)
+            {
+                let rate = doppler(saucer.pos, saucer.vel, game.bounds);
+                let sfx = Sfx::SaucerWarble(saucer.size);
+                wanted.push(Self::variant(sfx, Some(saucer.pos), game.bounds, rate));
+            }
+        }
+
+        for &(variant, _) in &self.playing {
+            if !wanted.iter().any(|(v, _)| *v == variant) {
+                if let Some(sound) = self.sound(variant) {
+                    stop_sound(sound);
+                }
+            }
+        }
+        for &(variant, gain) in &wanted {
+            match self.playing.iter().find(|(v, _)| *v == variant) {
+                None => self.play((variant, gain)),
+                Some(&(_, playing_gain)) if playing_gain != gain => {
+                    if let Some(sound) = self.sound(variant) {
+                        set_sound_volume(sound, self.volume(variant.sfx, gain));
+                    }
+                }
+                Some(_) => (),
+            }
+        }
+        self.playing = wanted;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rollback::tests::SIMULATION, Asteroid};
+
+    #[test]
+    fn heartbeat_quickens_through_the_wave() {
+        // asteroids get their shape from macroquad's shared random numbers
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = Game::new(1, Vec2::new(800.0, 600.0));
+        let start = beat_interval(&game);
+        assert_eq!(start, MUSIC_SLOWEST_BEAT);
+
+        // every asteroid of the wave is out, and they get picked off one by one
+        game.asteroids_spawned_in_wave = game.get_wave_asteroid_amount();
+        game.asteroids = (0..6)
+            .map(|_| Asteroid::new(AsteroidType::Small, Vec2::ZERO, Vec2::ZERO))
+            .collect();
+        let crowded = beat_interval(&game);
+        game.asteroids.truncate(1);
+        let last = beat_interval(&game);
+        assert!(start > crowded && crowded > last);
+
+        // and the same point in later waves is quicker still
+        game.asteroid_wave = 5;
+        game.asteroids_spawned_in_wave = game.get_wave_asteroid_amount();
+        assert!(beat_interval(&game) < last);
+        game.asteroid_wave = 100;
+        game.asteroids_spawned_in_wave = 0;
+        assert_eq!(beat_interval(&game), MUSIC_FASTEST_BEAT);
+    }
+
+    #[test]
+    fn sounds_follow_what_makes_them() {
+        let bounds = Vec2::new(800.0, 600.0);
+        let (left, left_gain) = placement(Vec2::new(0.0, 300.0), bounds);
+        let (middle, middle_gain) = placement(Vec2::new(400.0, 0.0), bounds);
+        assert_eq!((left, middle), (-STEREO_WIDTH, 0.0));
+        assert!(left_gain < middle_gain);
+        assert_eq!(pan_step(left), 0);
+        assert_eq!(pan_step(-left), STEREO_PAN_STEPS - 1);
+
+        let (variant, _) = Audio::variant(Sfx::Laser, Some(Vec2::new(790.0, 0.0)), bounds, 1.0);
+        assert_eq!(variant.pan, STEREO_PAN_STEPS - 1);
+        // music stays in the middle wherever it is asked for
+        let (variant, gain) = Audio::variant(Sfx::BeatLow, Some(Vec2::ZERO), bounds, 1.0);
+        assert_eq!((step_pan(variant.pan), gain), (0.0, 1.0));
+
+        // a saucer rises in pitch flying in from the left, evens out passing the middle and
+        // falls away as it leaves to the right
+        let vel = Vec2::new(100.0, 0.0);
+        let rates = [50.0, 390.0, 750.0].map(|x| doppler(Vec2::new(x, 300.0), vel, bounds));
+        assert_eq!(rates.map(pitch_step), [2, 1, 0]);
+    }
+}
@@ -0,0 +1,117 @@
+//! Headless training environment.
+//!
+//! `gym bench [episodes] [max steps]` plays episodes of random actions as fast as it can and
+//! reports the throughput.
+//!
+//! `gym serve [width height]` drives the environment from stdin for agents in other languages.
+//! Each line is either `reset <seed>` or `step <action>`, and each is answered with one line of
+//! JSON holding the observation, reward and whether the episode is done, plus the rasterised
+//! frame when a size was given.
+
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    time::Instant,
+};
+
+use asteroids_wasm::gym::{Env, Observation};
+
+const USAGE: &str = "usage: gym bench [episodes] [max steps] | gym serve [width height]";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    // none when the argument isn't a number
+    let number = |i: usize, default: u64| args.get(i).map_or(Some(default), |a| a.parse().ok());
+
+    match args.first().map(String::as_str) {
+        Some("bench") => match (number(1, 1000), number(2, 600)) {
+            (Some(episodes), Some(max_steps)) => bench(episodes, max_steps as u32),
+            _ => eprintln!("{USAGE}"),
+        },
+        Some("serve") => {
+            // frames are only rasterised when both sides are given, and neither can be empty
+            let raster = match (args.len() > 2, number(1, 0), number(2, 0)) {
+                (false, ..) => None,
+                (true, Some(width @ 1..), Some(height @ 1..)) => {
+                    Some((width as usize, height as usize))
+                }
+                _ => return eprintln!("{USAGE}"),
+            };
+            if let Err(e) = serve(Env::new(raster)) {
+                eprintln!("gym: {e}");
+            }
+        }
+        _ => eprintln!("{USAGE}"),
+    }
+}
+
+fn bench(episodes: u64, max_steps: u32) {
+    let mut env = Env::new(None);
+    // actions from a generator of our own, the game's is reseeded every step
+    let mut random: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut steps = 0u64;
+    let mut total_reward = 0.0;
+
+    let start = Instant::now();
+    for seed in 0..episodes {
+        env.reset(seed);
+        for _ in 0..max_steps {
+            random ^= random << 13;
+            random ^= random >> 7;
+            random ^= random << 17;
+            let (_, reward, done) = env.step(random as u8 & 0b11_1111);
+            steps += 1;
+            total_reward += reward as f64;
+            if done {
+                break;
+            }
+        }
+    }
+    let seconds = start.elapsed().as_secs_f64();
+
+    println!(
+        "{episodes} episodes, {steps} steps in {seconds:.2}s: {:.0} episodes/s, {:.0} steps/s, mean reward {:.1}",
+        episodes as f64 / seconds,
+        steps as f64 / seconds,
+        total_reward / episodes as f64
+    );
+}
+
+fn serve(mut env: Env) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let (command, value) = (words.next(), words.next().and_then(|v| v.parse().ok()));
+        let (observation, reward, done) = match (command, value) {
+            (Some("reset"), Some(seed)) => (env.reset(seed), 0.0, false),
+            (Some("step"), Some(action)) if action < 64 => env.step(action as u8),
+            _ => {
+                writeln!(
+                    out,
+                    "{{\"error\":\"expected reset <seed> or step <action>\"}}"
+                )?;
+                continue;
+            }
+        };
+        writeln!(out, "{}", to_json(&observation, reward, done))?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn to_json(observation: &Observation, reward: f32, done: bool) -> String {
+    let list = |values: Vec<String>| values.join(",");
+    let mut json = format!(
+        "{{\"observation\":[{}],\"reward\":{reward},\"done\":{done}",
+        list(observation.vector.iter().map(f32::to_string).collect())
+    );
+    if let Some(frame) = &observation.frame {
+        json += &format!(
+            ",\"frame\":[{}]",
+            list(frame.iter().map(u8::to_string).collect())
+        );
+    }
+    json + "}"
+}
@@ -0,0 +1,169 @@
+//! Batch balance simulation.
+//!
+//! Plays games with the computer pilot for every combination of the given tuning values and
+//! prints one row of statistics per combination, as CSV or JSON:
+//!
+//! `simulate --saucer-spawn-rate 5,10,20 --ship-max-speed 60,80 --games 50 --format json`
+//!
+//! Values not given keep the game's own. `--games` is the number of games per combination,
+//! seeded from zero upwards so each combination sees the same games, and `--max-time` cuts
+//! games short after that many seconds.
+
+use std::{env, process};
+
+use asteroids_wasm::{
+    simulate::{simulate, Summary},
+    Tuning,
+};
+
+const USAGE: &str = "usage: simulate [--saucer-spawn-rate a,b,..] \
+[--asteroid-spawn-decrease-factor a,b,..] [--ship-max-speed a,b,..] [--games n] \
+[--max-time seconds] [--format csv|json]";
+
+fn main() {
+    let defaults = Tuning::default();
+    let mut saucer_spawn_rates = vec![defaults.saucer_spawn_rate];
+    let mut decrease_factors = vec![defaults.asteroid_spawn_decrease_factor];
+    let mut max_speeds = vec![defaults.ship_max_speed];
+    let mut games = 20;
+    let mut max_time = 600.0;
+    let mut json = false;
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    for pair in args.chunks(2) {
+        let [flag, value] = pair else {
+            fail(&format!("{} needs a value", pair[0]));
+        };
+        let list = || -> Vec<f32> {
+            value
+                .split(',')
+                .map(|v| {
+                    v.trim()
+                        .parse()
+                        .unwrap_or_else(|_| fail(&format!("bad value {v}")))
+                })
+                .collect()
+        };
+        match flag.as_str() {
+            "--saucer-spawn-rate" => saucer_spawn_rates = list(),
+            "--asteroid-spawn-decrease-factor" => decrease_factors = list(),
+            "--ship-max-speed" => max_speeds = list(),
+            "--games" => {
+                games = value
+                    .parse()
+                    .unwrap_or_else(|_| fail("bad number of games"))
+            }
+            "--max-time" => max_time = value.parse().unwrap_or_else(|_| fail("bad time limit")),
+            "--format" => match value.as_str() {
+                "csv" => json = false,
+                "json" => json = true,
+                _ => fail("format should be csv or json"),
+            },
+            _ => fail(&format!("unknown option {flag}")),
+        }
+    }
+
+    let mut first = true;
+    for &saucer_spawn_rate in &saucer_spawn_rates {
+        for &asteroid_spawn_decrease_factor in &decrease_factors {
+            for &ship_max_speed in &max_speeds {
+                let tuning = Tuning {
+                    saucer_spawn_rate,
+                    asteroid_spawn_decrease_factor,
+                    ship_max_speed,
+                };
+                let stats: Vec<_> = (0..games)
+                    .map(|seed| simulate(tuning, seed, max_time))
+                    .collect();
+                let summary = Summary::new(tuning, &stats);
+                if json {
+                    println!("{}{}", if first { "[" } else { "," }, to_json(&summary));
+                } else {
+                    if first {
+                        println!("{}", csv_header(&summary));
+                    }
+                    println!("{}", to_csv(&summary));
+                }
+                first = false;
+            }
+        }
+    }
+    if json {
+        println!("{}", if first { "[]" } else { "]" });
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("simulate: {message}\n{USAGE}");
+    process::exit(1);
+}
+
+fn csv_header(summary: &Summary) -> String {
+    let causes = |prefix: &str, deaths: &[(&str, usize)]| {
+        deaths
+            .iter()
+            .map(|(name, _)| format!("{prefix}{}", name.replace(' ', "_")))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    format!(
+        "saucer_spawn_rate,asteroid_spawn_decrease_factor,ship_max_speed,games,\
+mean_survival_time,median_survival_time,mean_wave,max_wave,mean_score,\
+min_score,p25_score,median_score,p75_score,max_score,{},{}",
+        causes("deaths_", &summary.deaths),
+        causes("final_", &summary.final_deaths)
+    )
+}
+
+fn to_csv(s: &Summary) -> String {
+    let counts = |deaths: &[(&str, usize)]| {
+        deaths
+            .iter()
+            .map(|(_, count)| count.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let [min, p25, median, p75, max] = s.score_quartiles;
+    format!(
+        "{},{},{},{},{:.2},{:.2},{:.2},{},{:.1},{min},{p25},{median},{p75},{max},{},{}",
+        s.tuning.saucer_spawn_rate,
+        s.tuning.asteroid_spawn_decrease_factor,
+        s.tuning.ship_max_speed,
+        s.games,
+        s.mean_survival_time,
+        s.median_survival_time,
+        s.mean_wave,
+        s.max_wave,
+        s.mean_score,
+        counts(&s.deaths),
+        counts(&s.final_deaths)
+    )
+}
+
+fn to_json(s: &Summary) -> String {
+    let counts = |deaths: &[(&str, usize)]| {
+        deaths
+            .iter()
+            .map(|(name, count)| format!("\"{name}\":{count}"))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let [min, p25, median, p75, max] = s.score_quartiles;
+    format!(
+        "{{\"tuning\":{{\"saucer_spawn_rate\":{},\"asteroid_spawn_decrease_factor\":{},\"ship_max_speed\":{}}},\
+\"games\":{},\"survival_time\":{{\"mean\":{:.2},\"median\":{:.2}}},\"wave\":{{\"mean\":{:.2},\"max\":{}}},\
+\"score\":{{\"mean\":{:.1},\"min\":{min},\"p25\":{p25},\"median\":{median},\"p75\":{p75},\"max\":{max}}},\
+\"deaths\":{{{}}},\"final_deaths\":{{{}}}}}",
+        s.tuning.saucer_spawn_rate,
+        s.tuning.asteroid_spawn_decrease_factor,
+        s.tuning.ship_max_speed,
+        s.games,
+        s.mean_survival_time,
+        s.median_survival_time,
+        s.mean_wave,
+        s.max_wave,
+        s.mean_score,
+        counts(&s.deaths),
+        counts(&s.final_deaths)
+    )
+}
@@ -0,0 +1,163 @@
+//! Feedback through the camera: shaking on heavy impacts, freezing for a moment when a saucer is
+//! shot down and zooming in and out on an extra life
+
+use std::f32::consts::PI;
+
+use macroquad::prelude::*;
+
+use crate::{
+    events::GameEvent, settings::Settings, AsteroidType, CAMERA_HIT_STOP, CAMERA_SHAKE_ANGLE,
+    CAMERA_SHAKE_FREQUENCY, CAMERA_SHAKE_OFFSET, CAMERA_TRAUMA_DECAY,
+    CAMERA_TRAUMA_LARGE_EXPLOSION, CAMERA_TRAUMA_SHIP_HIT, CAMERA_ZOOM_PULSE,
+    CAMERA_ZOOM_PULSE_TIME,
+};
+
+/// Smooth wobble between -1 and 1, different for each seed. Made from sines rather than random
+/// numbers so the shake doesn't change how a game plays out
+fn wobble(time: f32, seed: f32) -> f32 {
+    let t = time * CAMERA_SHAKE_FREQUENCY;
+    0.6 * (t + seed).sin() + 0.4 * (t * 2.3 + seed * 1.7).sin()
+}
+
+pub struct CameraEffects {
+    // from the settings, how hard to shake and which effects are wanted
+    shake: f32,
+    hit_stop: bool,
+    zoom_pulse: bool,
+
+    // zero to one, how shaken up the camera is
+    trauma: f32,
+    // seconds the camera has been running, for the wobble
+    time: f32,
+    // seconds left of the current hit-stop
+    stopped: f32,
+    // seconds into the zoom pulse, while there is one
+    pulse: Option<f32>,
+}
+
+impl CameraEffects {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            shake: settings.screen_shake,
+            hit_stop: settings.hit_stop,
+            zoom_pulse: settings.zoom_pulse,
+            trauma: 0.0,
+            time: 0.0,
+            stopped: 0.0,
+            pulse: None,
+        }
+    }
+
+    /// Reacts to what happened in play, only stopping play if it can be held up
+    pub fn handle(&mut self, events: &[GameEvent], can_stop: bool) {
+        for event in events {
+            match event {
+                GameEvent::ShipHit { .. } => self.add_trauma(CAMERA_TRAUMA_SHIP_HIT),
+                GameEvent::AsteroidDestroyed {
+                    size: AsteroidType::Large,
+                    ..
+                } => self.add_trauma(CAMERA_TRAUMA_LARGE_EXPLOSION),
+                GameEvent::SaucerDestroyed { by, .. } => {
+                    self.add_trauma(CAMERA_TRAUMA_LARGE_EXPLOSION);
+                    if by.is_some() && can_stop && self.hit_stop {
+                        self.stopped = CAMERA_HIT_STOP;
+                    }
+                }
+                GameEvent::ExtraLife { .. } if self.zoom_pulse => self.pulse = Some(0.0),
+                _ => (),
+            }
+        }
+    }
+
+    fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+
+    /// Runs on in real time, whether or not play is
+    pub fn update(&mut self, frame_time: f32) {
+        self.time += frame_time;
+        self.trauma = (self.trauma - CAMERA_TRAUMA_DECAY * frame_time).max(0.0);
+        self.stopped = (self.stopped - frame_time).max(0.0);
+        self.pulse = self
+            .pulse
+            .map(|t| t + frame_time)
+            .filter(|t| *t < CAMERA_ZOOM_PULSE_TIME);
+    }
+
+    /// Whether play should be held still this frame
+    pub fn hit_stopped(&self) -> bool {
+        self.stopped > 0.0
+    }
+
+    /// The camera moved by the shake and zoomed by the pulse
+    pub fn apply(&self, camera: Camera2D) -> Camera2D {
+        let shake = self.trauma * self.trauma * self.shake;
+        let offset = Vec2::new(wobble(self.time, 0.0), wobble(self.time, 10.0));
+        let zoom = self.pulse.map_or(1.0, |t| {
+            1.0 + CAMERA_ZOOM_PULSE * (t / CAMERA_ZOOM_PULSE_TIME * PI).sin()
+        });
+        Camera2D {
+            target: camera.target + offset * CAMERA_SHAKE_OFFSET * shake,
+            rotation: camera.rotation + wobble(self.time, 20.0) * CAMERA_SHAKE_ANGLE * shake,
+            zoom: camera.zoom * zoom,
+            ..camera
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeathCause, Kill, SaucerSize, TIME_STEP};
+
+    #[test]
+    fn impacts_move_the_camera_until_they_wear_off() {
+        let playfield = || Camera2D::from_display_rect(Rect::new(0.0, 0.0, 800.0, 600.0));
+        let camera = playfield();
+        let mut effects = CameraEffects::new(&Settings::default());
+        let hit = GameEvent::ShipHit {
+            player: 0,
+            cause: DeathCause::Saucer,
+            pos: Vec2::ZERO,
+        };
+        effects.handle(&[hit], true);
+        effects.update(TIME_STEP);
+        let shaken = effects.apply(playfield());
+        assert_ne!(shaken.target, camera.target);
+        assert_ne!(shaken.rotation, camera.rotation);
+        for _ in 0..120 {
+            effects.update(TIME_STEP);
+        }
+        assert_eq!(effects.apply(playfield()).target, camera.target);
+
+        // no shake at all when it's turned down
+        let mut still = CameraEffects::new(&Settings {
+            screen_shake: 0.0,
+            ..Default::default()
+        });
+        still.handle(&[hit], true);
+        still.update(TIME_STEP);
+        assert_eq!(still.apply(playfield()).target, camera.target);
+
+        // shooting a saucer down holds play for a moment, unless it's online
+        let kill = GameEvent::SaucerDestroyed {
+            size: SaucerSize::Small,
+            by: Some(0),
+            kill: Some(Kill::Shot { shots: 1 }),
+            pos: Vec2::ZERO,
+        };
+        effects.handle(&[kill], false);
+        assert!(!effects.hit_stopped());
+        effects.handle(&[kill], true);
+        assert!(effects.hit_stopped());
+        effects.update(CAMERA_HIT_STOP);
+        assert!(!effects.hit_stopped());
+
+        // an extra life zooms in and back out
+        effects.handle(&[GameEvent::ExtraLife { player: 0 }], true);
+        effects.update(CAMERA_ZOOM_PULSE_TIME / 2.0);
+        assert!(effects.apply(playfield()).zoom.x > camera.zoom.x);
+        effects.update(CAMERA_ZOOM_PULSE_TIME);
+        assert_eq!(effects.apply(playfield()).zoom, camera.zoom);
+    }
+}
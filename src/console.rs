@@ -0,0 +1,253 @@
+//! Drop-down dev console for changing a game while it is being played
+
+use std::str::FromStr;
+
+use macroquad::{prelude::*, rand};
+
+use crate::{
+    random_edge_position, random_unit_vector, render::Frame, Asteroid, AsteroidType, Game,
+    SaucerSize, TimeControl, CONSOLE_COLOR, CONSOLE_LINES,
+};
+
+const HELP: &str = "spawn asteroid small|medium|large, spawn saucer small|large, god, wave <n>, \
+                    lives <n>, timescale <scale>, seed <n>";
+
+#[derive(Default)]
+pub struct Console {
+    open: bool,
+    input: String,
+    // commands entered and the replies to them, oldest first
+    lines: Vec<String>,
+}
+
+fn number<T: FromStr>(word: &str) -> Result<T, String> {
+    word.parse().map_err(|_| format!("{word} isn't a number"))
+}
+
+impl Console {
+    /// Whether play waits while the console is open, which only a local game can do. An online
+    /// game stalls for everyone once the other players run out of inputs
+    pub fn holds_play(&self, local: bool) -> bool {
+        self.open && local
+    }
+
+    /// Opens and closes with the backtick key and takes typing while open, running commands
+    /// against the game if there is one that can be changed
+    pub fn update(&mut self, game: Option<&mut Game>, time: &mut TimeControl) {
+        if is_key_pressed(KeyCode::GraveAccent) {
+            self.open = !self.open;
+        }
+        if !self.open {
+            return;
+        }
+
+        while let Some(c) = get_char_pressed() {
+            if !c.is_control() && c != '`' {
+                self.input.push(c);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.input.pop();
+        }
+        if is_key_pressed(KeyCode::Enter) && !self.input.trim().is_empty() {
+            let command = std::mem::take(&mut self.input);
+            let reply = match self.run(&command, game, time) {
+                Ok(reply) => reply,
+                Err(e) => e,
+            };
+            self.lines.push(format!("> {command}"));
+            self.lines.push(reply);
+        }
+    }
+
+    /// Carries out a command, replying with what it did
+    pub fn run(
+        &mut self,
+        command: &str,
+        game: Option<&mut Game>,
+        time: &mut TimeControl,
+    ) -> Result<String, String> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        // commands that don't need a game
+        match words[..] {
+            ["help"] => return Ok(HELP.to_string()),
+            ["seed", seed] => {
+                let seed = number(seed)?;
+                rand::srand(seed);
+                return Ok(format!("random numbers seeded with {seed}"));
+            }
+            ["timescale", scale] => {
+                let scale: f32 = number(scale)?;
+                if scale <= 0.0 || !scale.is_finite() {
+                    return Err("the time scale has to be above zero".to_string());
+                }
+                time.scale = scale;
+                return Ok(format!("local games run at {scale}x"));
+            }
+            _ => (),
+        }
+
+        // online games can't be changed without the other players going out of step
+        let game = game.ok_or("there is no local game to change")?;
+        let reply = match words[..] {
+            ["spawn", "asteroid", size] => {
+                let size = [
+                    AsteroidType::Small,
+                    AsteroidType::Medium,
+                    AsteroidType::Large,
+                ]
+                .into_iter()
+                .find(|s| s.name() == size)
+                .ok_or(format!("there are no {size} asteroids"))?;
+                game.asteroids.push(Asteroid::new(
+                    size,
+                    random_edge_position(game.bounds),
+                    random_unit_vector() * size.speed(),
+                ));
+                format!("spawned a {size} asteroid", size = size.name())
+            }
+            ["spawn", "saucer", size] => {
+                let size = [SaucerSize::Small, SaucerSize::Large]
+                    .into_iter()
+                    .find(|s| s.name() == size)
+                    .ok_or(format!("there are no {size} saucers"))?;
+                game.spawn_saucer(size);
+                format!("spawned a {size} saucer", size = size.name())
+            }
+            ["god"] => {
+                game.god = !game.god;
+                format!("god mode {}", if game.god { "on" } else { "off" })
+            }
+            ["wave", wave] => {
+                let wave = number(wave)?;
+                if wave == 0 {
+                    return Err("waves count from 1".to_string());
+                }
+                game.asteroids.clear();
+                game.start_wave(wave);
+                format!("started wave {wave}")
+            }
+            ["lives", lives] => {
+                let lives = number(lives)?;
+                if lives == 0 {
+                    return Err("there has to be at least one life".to_string());
+                }
+                // anyone already out comes back in on their next respawn
+                game.players.iter_mut().for_each(|p| {
+                    p.lives.set_remaining(lives);
+                    p.out_of_lives = false;
+                });
+                format!("everyone has {lives} lives")
+            }
+            _ => return Err(format!("don't know how to {command}, try help")),
+        };
+        game.cheated = true;
+        Ok(reply)
+    }
+
+    /// The console over the top of the screen, while it is open
+    /// Dropped down over the top of the screen, the cursor blinking with the time in seconds
+    pub fn frame(&self, screen: Vec2, time: f64) -> Frame {
+        let mut frame = Frame::default();
+        if !self.open {
+            return frame;
+        }
+
+        let line_height = 18.0;
+        let height = (CONSOLE_LINES + 1) as f32 * line_height + 12.0;
+        frame.rectangle(0.0, 0.0, screen.x, height, Color::new(0.0, 0.0, 0.0, 0.85));
+        frame.line(
+            Vec2::new(0.0, height),
+            Vec2::new(screen.x, height),
+            1.0,
+            CONSOLE_COLOR,
+        );
+
+        let shown = self.lines.len().saturating_sub(CONSOLE_LINES);
+        for (i, line) in self.lines[shown..].iter().enumerate() {
+            let y = line_height * (i + 1) as f32;
+            frame.text(line, Vec2::new(8.0, y), line_height, LIGHTGRAY);
+        }
+        let cursor = if time.fract() < 0.5 { "_" } else { "" };
+        frame.text(
+            &format!("> {}{cursor}", self.input),
+            Vec2::new(8.0, height - 8.0),
+            line_height,
+            CONSOLE_COLOR,
+        );
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render::Primitive, rollback::tests::SIMULATION};
+
+    #[test]
+    fn only_local_games_wait_for_the_console() {
+        let mut console = Console::default();
+        assert!(!console.holds_play(true));
+        console.open = true;
+        assert!(console.holds_play(true));
+        // the other players in an online game carry on regardless
+        assert!(!console.holds_play(false));
+    }
+
+    #[test]
+    fn the_console_is_only_drawn_open() {
+        let mut console = Console {
+            input: "god".to_string(),
+            ..Default::default()
+        };
+        let screen = Vec2::new(800.0, 600.0);
+        assert!(console.frame(screen, 0.0).primitives.is_empty());
+        console.open = true;
+        let prompt = |frame: Frame| {
+            frame.primitives.into_iter().find_map(|p| match p {
+                Primitive::Text { text, .. } if text.starts_with('>') => Some(text),
+                _ => None,
+            })
+        };
+        // with the cursor blinking
+        assert_eq!(prompt(console.frame(screen, 0.0)).unwrap(), "> god_");
+        assert_eq!(prompt(console.frame(screen, 0.5)).unwrap(), "> god");
+    }
+
+    #[test]
+    fn commands_change_the_local_game() {
+        // asteroids and saucers are placed with macroquad's shared random numbers
+        let _lock = SIMULATION.lock().unwrap();
+        let mut console = Console::default();
+        let mut time = TimeControl::default();
+        let mut game = Game::new(1, Vec2::new(800.0, 600.0));
+
+        console
+            .run("spawn asteroid large", Some(&mut game), &mut time)
+            .unwrap();
+        console
+            .run("spawn saucer small", Some(&mut game), &mut time)
+            .unwrap();
+        assert_eq!(game.asteroids[0].size, AsteroidType::Large);
+        assert_eq!(game.saucers[0].size, SaucerSize::Small);
+
+        console.run("wave 10", Some(&mut game), &mut time).unwrap();
+        assert!(game.asteroids.is_empty());
+        assert_eq!(game.asteroid_wave, 9);
+        game.players[0].out_of_lives = true;
+        console.run("lives 9", Some(&mut game), &mut time).unwrap();
+        assert_eq!(game.players[0].lives.remaining(), 9);
+        assert!(!game.players[0].out_of_lives);
+        console.run("god", Some(&mut game), &mut time).unwrap();
+        assert!(game.god && game.cheated);
+
+        console.run("timescale 0.25", None, &mut time).unwrap();
+        assert_eq!(time.scale, 0.25);
+        assert!(console.run("timescale -1", None, &mut time).is_err());
+        assert!(console
+            .run("spawn asteroid huge", Some(&mut game), &mut time)
+            .is_err());
+        // nothing to change in an online game
+        assert!(console.run("god", None, &mut time).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! What happens during a game, reported as it happens so anything interested can react to it
+
+use std::io::{self, Write};
+
+use macroquad::prelude::*;
+
+use crate::{AsteroidType, DeathCause, Kill, SaucerSize};
+
+/// Something that happened during an update, players are given by their index and anything with
+/// a place on the playfield says where it happened
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    /// An asteroid was broken up, by the player who shot or rammed it if anyone did
+    AsteroidDestroyed {
+        size: AsteroidType,
+        by: Option<usize>,
+        pos: Vec2,
+    },
+    SaucerSpawned {
+        size: SaucerSize,
+        pos: Vec2,
+    },
+    /// A saucer was brought down, by the player who shot or rammed it if anyone did
+    SaucerDestroyed {
+        size: SaucerSize,
+        by: Option<usize>,
+        kill: Option<Kill>,
+        pos: Vec2,
+    },
+    ShipHit {
+        player: usize,
+        cause: DeathCause,
+        pos: Vec2,
+    },
+    /// Waves count from one
+    WaveStarted {
+        wave: usize,
+    },
+    BulletFired {
+        player: usize,
+        pos: Vec2,
+    },
+    /// The ship jumped away from the position
+    HyperspaceUsed {
+        player: usize,
+        pos: Vec2,
+    },
+    ExtraLife {
+        player: usize,
+    },
+    GameOver,
+}
+
+impl GameEvent {
+    /// Where it happened, none for what happens to the game as a whole
+    pub fn pos(&self) -> Option<Vec2> {
+        match *self {
+            Self::AsteroidDestroyed { pos, .. }
+            | Self::SaucerSpawned { pos, .. }
+            | Self::SaucerDestroyed { pos, .. }
+            | Self::ShipHit { pos, .. }
+            | Self::BulletFired { pos, .. }
+            | Self::HyperspaceUsed { pos, .. } => Some(pos),
+            Self::WaveStarted { .. } | Self::ExtraLife { .. } | Self::GameOver => None,
+        }
+    }
+
+    /// The event as a single line JSON object, tagged with the game time it happened at
+    pub fn to_json(self, time: f32) -> String {
+        let player = |player: Option<usize>| player.map_or("null".to_string(), |p| p.to_string());
+        let fields = match self {
+            Self::AsteroidDestroyed { size, by, .. } => format!(
+                "\"event\":\"asteroid_destroyed\",\"size\":\"{}\",\"by\":{}",
+                size.name(),
+                player(by)
+            ),
+            Self::SaucerSpawned { size, .. } => {
+                format!("\"event\":\"saucer_spawned\",\"size\":\"{}\"", size.name())
+            }
+            Self::SaucerDestroyed { size, by, kill, .. } => format!(
+                "\"event\":\"saucer_destroyed\",\"size\":\"{}\",\"by\":{},\"kill\":{}",
+                size.name(),
+                player(by),
+                kill.map_or("null".to_string(), |kill| format!("\"{}\"", kill.name()))
+            ),
+            Self::ShipHit { player, cause, .. } => format!(
+                "\"event\":\"ship_hit\",\"player\":{player},\"cause\":\"{}\"",
+                cause.name()
+            ),
+            Self::WaveStarted { wave } => format!("\"event\":\"wave_started\",\"wave\":{wave}"),
+            Self::BulletFired { player, .. } => {
+                format!("\"event\":\"bullet_fired\",\"player\":{player}")
+            }
+            Self::HyperspaceUsed { player, .. } => {
+                format!("\"event\":\"hyperspace_used\",\"player\":{player}")
+            }
+            Self::ExtraLife { player } => format!("\"event\":\"extra_life\",\"player\":{player}"),
+            Self::GameOver => "\"event\":\"game_over\"".to_string(),
+        };
+        let pos = self
+            .pos()
+            .map(|pos| format!(",\"x\":{:.1},\"y\":{:.1}", pos.x, pos.y))
+            .unwrap_or_default();
+        format!("{{\"time\":{time:.3},{fields}{pos}}}")
+    }
+}
+
+/// Writes events out as JSON lines, for looking over games after they have been played
+pub struct EventLog {
+    out: Box<dyn Write>,
+}
+
+impl EventLog {
+    pub fn new(out: impl Write + 'static) -> Self {
+        Self { out: Box::new(out) }
+    }
+
+    pub fn write(&mut self, time: f32, events: &[GameEvent]) -> io::Result<()> {
+        for event in events {
+            writeln!(self.out, "{}", event.to_json(time))?;
+        }
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    // a writer the test can still read from once the log owns it
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn events_are_logged_one_json_object_per_line() {
+        let written = Shared::default();
+        let mut log = EventLog::new(written.clone());
+        log.write(
+            1.5,
+            &[
+                GameEvent::AsteroidDestroyed {
+                    size: AsteroidType::Medium,
+                    by: Some(1),
+                    pos: Vec2::new(12.0, 34.5),
+                },
+                GameEvent::ShipHit {
+                    player: 0,
+                    cause: DeathCause::SaucerBullet,
+                    pos: Vec2::new(400.0, 300.0),
+                },
+                GameEvent::SaucerDestroyed {
+                    size: SaucerSize::Small,
+                    by: Some(0),
+                    kill: Some(Kill::Rammed),
+                    pos: Vec2::new(20.0, 40.0),
+                },
+            ],
+        )
+        .unwrap();
+        log.write(2.0, &[GameEvent::GameOver]).unwrap();
+
+        let written = String::from_utf8(written.0.borrow().clone()).unwrap();
+        assert_eq!(
+            written.lines().collect::<Vec<_>>(),
+            [
+                "{\"time\":1.500,\"event\":\"asteroid_destroyed\",\"size\":\"medium\",\"by\":1,\"x\":12.0,\"y\":34.5}",
+                "{\"time\":1.500,\"event\":\"ship_hit\",\"player\":0,\"cause\":\"saucer bullet\",\"x\":400.0,\"y\":300.0}",
+                "{\"time\":1.500,\"event\":\"saucer_destroyed\",\"size\":\"small\",\"by\":0,\"kill\":\"rammed\",\"x\":20.0,\"y\":40.0}",
+                "{\"time\":2.000,\"event\":\"game_over\"}",
+            ]
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! Vector monitor look: the scene is drawn offscreen, lingers like phosphor that is slow to
+//! fade, blooms into its surroundings and is shown through faint scanlines
+
+use std::cell::RefCell;
+
+use macroquad::prelude::*;
+
+use crate::{GLOW_BLOOM_SPREAD, GLOW_BLOOM_STRENGTH, GLOW_PERSISTENCE, GLOW_SCANLINE_DEPTH};
+
+// half of a gaussian blur, the middle tap first and then each pair either side of it
+const BLUR_WEIGHTS: [f32; 5] = [0.227, 0.195, 0.122, 0.054, 0.016];
+
+// the taps are offset from the middle, more precision than the usual lowp is needed for them
+const VERTEX_SHADER: &str = "#version 100
+precision mediump float;
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying vec2 uv;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    uv = texcoord;
+}
+";
+
+// whatever was drawn this frame over what is left of the last one
+const PERSIST_SHADER: &str = "#version 100
+precision mediump float;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform sampler2D Previous;
+uniform float persistence;
+
+void main() {
+    vec3 previous = texture2D(Previous, uv).rgb * persistence;
+    gl_FragColor = vec4(max(texture2D(Texture, uv).rgb, previous), 1.0);
+}
+";
+
+// the trail with its bloom added, every other row darkened
+const COMPOSITE_SHADER: &str = "#version 100
+precision mediump float;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform sampler2D Bloom;
+uniform float strength;
+uniform float scanline_depth;
+
+void main() {
+    vec3 light = texture2D(Texture, uv).rgb + texture2D(Bloom, uv).rgb * strength;
+    float scanline = mod(floor(gl_FragCoord.y), 2.0) < 1.0 ? 1.0 : 1.0 - scanline_depth;
+    gl_FragColor = vec4(min(light * scanline, 1.0), 1.0);
+}
+";
+
+/// One direction of the blur, unrolled from BLUR_WEIGHTS as arrays can't be initialised in
+/// this version of GLSL
+fn blur_shader() -> String {
+    let taps: String = BLUR_WEIGHTS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, weight)| {
+            format!(
+                "    sum += (texture2D(Texture, uv + texel_step * {i}.0).rgb \
+                 + texture2D(Texture, uv - texel_step * {i}.0).rgb) * {weight:?};\n"
+            )
+        })
+        .collect();
+    format!(
+        "#version 100
+precision mediump float;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform vec2 texel_step;
+
+void main() {{
+    vec3 sum = texture2D(Texture, uv).rgb * {middle:?};
+{taps}    gl_FragColor = vec4(sum, 1.0);
+}}
+",
+        middle = BLUR_WEIGHTS[0]
+    )
+}
+
+fn material(
+    name: &str,
+    fragment: &str,
+    uniforms: Vec<UniformDesc>,
+    textures: Vec<String>,
+) -> Option<Material> {
+    let material = load_material(
+        ShaderSource::Glsl {
+            vertex: VERTEX_SHADER,
+            fragment,
+        },
+        MaterialParams {
+            uniforms,
+            textures,
+            ..Default::default()
+        },
+    );
+    material
+        .map_err(|e| eprintln!("can't build the glow's {name} pass: {e}"))
+        .ok()
+}
+
+/// Offscreen textures the passes go through, all the size of the window
+struct Targets {
+    size: (u32, u32),
+    scene: RenderTarget,
+    // the trail being drawn this frame and the one left from the last, swapped each frame
+    trails: [RenderTarget; 2],
+    // bloom blurred across and then down
+    bloom: [RenderTarget; 2],
+}
+
+impl Targets {
+    fn new(size: (u32, u32)) -> Self {
+        let target = || {
+            let target = render_target(size.0, size.1);
+            target.texture.set_filter(FilterMode::Linear);
+            target
+        };
+        let targets = Self {
+            size,
+            scene: target(),
+            trails: [target(), target()],
+            bloom: [target(), target()],
+        };
+        // the first frame's persistence pass fades the last trail, which has to start empty
+        for trail in &targets.trails {
+            set_camera(&target_camera(trail));
+            clear_background(BLACK);
+        }
+        targets
+    }
+}
+
+/// Camera over the whole of a target, upright when the target is then drawn as a texture
+fn target_camera(target: &RenderTarget) -> Camera2D {
+    let size = target.texture.size();
+    Camera2D {
+        zoom: vec2(2.0 / size.x, 2.0 / size.y),
+        target: size / 2.0,
+        render_target: Some(target.clone()),
+        ..Default::default()
+    }
+}
+
+/// Runs the source through the material onto every pixel of the target
+fn pass(source: &Texture2D, target: &RenderTarget, material: &Material) {
+    set_camera(&target_camera(target));
+    gl_use_material(material);
+    draw_texture(source, 0.0, 0.0, WHITE);
+    gl_use_default_material();
+}
+
+pub struct Glow {
+    persist: Material,
+    blur_across: Material,
+    blur_down: Material,
+    composite: Material,
+    // made on the first frame and again whenever the window changes size
+    targets: RefCell<Option<Targets>>,
+}
+
+impl Glow {
+    /// None if any of the passes can't be built, leaving the flat renderer
+    pub fn new() -> Option<Self> {
+        let blur = blur_shader();
+        let texel_step = || vec![UniformDesc::new("texel_step", UniformType::Float2)];
+        Some(Self {
+            persist: material(
+                "persistence",
+                PERSIST_SHADER,
+                vec![UniformDesc::new("persistence", UniformType::Float1)],
+                vec!["Previous".to_string()],
+            )?,
+            blur_across: material("blur", &blur, texel_step(), vec![])?,
+            blur_down: material("blur", &blur, texel_step(), vec![])?,
+            composite: material(
+                "composite",
+                COMPOSITE_SHADER,
+                vec![
+                    UniformDesc::new("strength", UniformType::Float1),
+                    UniformDesc::new("scanline_depth", UniformType::Float1),
+                ],
+                vec!["Bloom".to_string()],
+            )?,
+            targets: RefCell::new(None),
+        })
+    }
+
+    /// The camera turned to draw into the offscreen scene instead of the window
+    pub fn camera(&self, camera: Camera2D) -> Camera2D {
+        let size = (screen_width() as u32, screen_height() as u32);
+        let mut targets = self.targets.borrow_mut();
+        if targets.as_ref().is_none_or(|t| t.size != size) {
+            let spread = GLOW_BLOOM_SPREAD / Vec2::new(size.0 as f32, size.1 as f32);
+            self.blur_across
+                .set_uniform("texel_step", vec2(spread.x, 0.0));
+            self.blur_down
+                .set_uniform("texel_step", vec2(0.0, spread.y));
+            *targets = Some(Targets::new(size));
+        }
+        let scene = &targets.as_ref().unwrap().scene;
+        // render targets come out upside down with the screen's downward y
+        Camera2D {
+            zoom: vec2(camera.zoom.x, -camera.zoom.y),
+            render_target: Some(scene.clone()),
+            ..camera
+        }
+    }
+
+    /// Puts the scene drawn through camera() on the window, glowing
+    pub fn present(&self) {
+        let mut targets = self.targets.borrow_mut();
+        let Some(targets) = targets.as_mut() else {
+            return;
+        };
+
+        targets.trails.swap(0, 1);
+        let [trail, previous] = &targets.trails;
+        self.persist.set_uniform("persistence", GLOW_PERSISTENCE);
+        self.persist
+            .set_texture("Previous", previous.texture.clone());
+        pass(&targets.scene.texture, trail, &self.persist);
+        pass(&trail.texture, &targets.bloom[0], &self.blur_across);
+        pass(
+            &targets.bloom[0].texture,
+            &targets.bloom[1],
+            &self.blur_down,
+        );
+
+        set_default_camera();
+        self.composite.set_uniform("strength", GLOW_BLOOM_STRENGTH);
+        self.composite
+            .set_uniform("scanline_depth", GLOW_SCANLINE_DEPTH);
+        self.composite
+            .set_texture("Bloom", targets.bloom[1].texture.clone());
+        gl_use_material(&self.composite);
+        draw_texture_ex(
+            &trail.texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(screen_width(), screen_height())),
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+    }
+}
+
+/// Light across an image, unclamped while the passes work on it
+#[cfg(test)]
+#[derive(Clone)]
+struct Light {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+#[cfg(test)]
+impl Light {
+    fn from_image(image: &Image) -> Self {
+        let (width, height) = (image.width as usize, image.height as usize);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let c = image.get_pixel(x as u32, y as u32);
+                Vec3::new(c.r, c.g, c.b)
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Pixels off the edge take the colour of the nearest one, like a clamped texture
+    fn get(&self, x: isize, y: isize) -> Vec3 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    fn map(&self, f: impl Fn(usize, usize) -> Vec3) -> Self {
+        let pixels = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+        Self { pixels, ..*self }
+    }
+
+    fn blur(&self, direction: (isize, isize)) -> Self {
+        self.map(|x, y| {
+            let (x, y) = (x as isize, y as isize);
+            let mut sum = self.get(x, y) * BLUR_WEIGHTS[0];
+            for (i, weight) in BLUR_WEIGHTS.iter().enumerate().skip(1) {
+                let offset = (GLOW_BLOOM_SPREAD * i as f32).round() as isize;
+                let (dx, dy) = (direction.0 * offset, direction.1 * offset);
+                sum += (self.get(x + dx, y + dy) + self.get(x - dx, y - dy)) * *weight;
+            }
+            sum
+        })
+    }
+
+    fn to_image(&self) -> Image {
+        let mut image = Image::gen_image_color(self.width as u16, self.height as u16, BLACK);
+        for (i, light) in self.pixels.iter().enumerate() {
+            let light = light.min(Vec3::ONE);
+            let (x, y) = ((i % self.width) as u32, (i / self.width) as u32);
+            image.set_pixel(x, y, Color::new(light.x, light.y, light.z, 1.0));
+        }
+        image
+    }
+}
+
+/// The same passes worked out on the CPU, for checking the look without a GPU
+#[cfg(test)]
+#[derive(Default)]
+pub struct SoftwareGlow {
+    // light left on the screen after the last frame
+    trail: Option<Light>,
+}
+
+#[cfg(test)]
+impl SoftwareGlow {
+    /// Takes in a frame's scene, giving back how it looks on the monitor
+    pub fn frame(&mut self, scene: &Image) -> Image {
+        let scene = Light::from_image(scene);
+        let trail = match &self.trail {
+            Some(previous) if previous.pixels.len() == scene.pixels.len() => scene.map(|x, y| {
+                let (x, y) = (x as isize, y as isize);
+                scene.get(x, y).max(previous.get(x, y) * GLOW_PERSISTENCE)
+            }),
+            _ => scene,
+        };
+        let bloom = trail.blur((1, 0)).blur((0, 1));
+        // rows are counted up from the bottom on the GPU
+        let shown = trail.map(|x, y| {
+            let i = y * trail.width + x;
+            let scanline = if (trail.height - 1 - y) % 2 == 0 {
+                1.0
+            } else {
+                1.0 - GLOW_SCANLINE_DEPTH
+            };
+            (trail.pixels[i] + bloom.pixels[i] * GLOW_BLOOM_STRENGTH) * scanline
+        });
+        self.trail = Some(trail);
+        shown.to_image()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        render::{
+            tests::{assert_golden, golden_scene},
+            Canvas,
+        },
+        rollback::tests::SIMULATION,
+        BACKGROUND_COLOR,
+    };
+
+    fn brightness(image: &Image, x: u32, y: u32) -> f32 {
+        let c = image.get_pixel(x, y);
+        c.r.max(c.g).max(c.b)
+    }
+
+    #[test]
+    fn lines_bloom_and_linger() {
+        // a dim line across the middle of an otherwise empty screen
+        let (width, height) = (64, 32);
+        let mut scene = Image::gen_image_color(width, height, BLACK);
+        for x in 16..48 {
+            scene.set_pixel(x, 15, Color::new(0.0, 0.4, 0.0, 1.0));
+        }
+        let mut glow = SoftwareGlow::default();
+        let shown = glow.frame(&scene);
+
+        // the bloom lights up either side of the line, less the further out, and only green
+        let line = brightness(&shown, 32, 15);
+        assert!(line > 0.4);
+        let near = brightness(&shown, 32, 13);
+        let far = brightness(&shown, 32, 9);
+        assert!(line > near && near > far && far > 0.0);
+        assert_eq!(shown.get_pixel(32, 13).r, 0.0);
+        assert_eq!(brightness(&shown, 0, 0), 0.0);
+
+        // with nothing drawn the line fades away over the following frames
+        let empty = Image::gen_image_color(width, height, BLACK);
+        let faded = brightness(&glow.frame(&empty), 32, 15);
+        assert!(faded < line && faded > 0.0);
+        for _ in 0..60 {
+            glow.frame(&empty);
+        }
+        assert_eq!(brightness(&glow.frame(&empty), 32, 15), 0.0);
+
+        // an even grey shows the scanlines, the bottom row being a bright one
+        let grey = Image::gen_image_color(width, height, Color::new(0.3, 0.3, 0.3, 1.0));
+        let shown = SoftwareGlow::default().frame(&grey);
+        let (bright, dark) = (brightness(&shown, 5, 31), brightness(&shown, 5, 30));
+        assert!((dark / bright - (1.0 - GLOW_SCANLINE_DEPTH)).abs() < 0.02);
+    }
+
+    #[test]
+    fn playfield_glow_matches_golden_image() {
+        let _lock = SIMULATION.lock().unwrap();
+        let scene = golden_scene();
+        let mut glow = SoftwareGlow::default();
+        assert_golden(&glow.frame(scene.image()), "playfield_glow");
+
+        // with everything gone from the playfield only its trail is left
+        let (width, height) = (scene.image().width, scene.image().height);
+        let empty = Canvas::new(
+            width,
+            height,
+            Rect::new(0.0, 0.0, 800.0, 600.0),
+            BACKGROUND_COLOR,
+        );
+        assert_golden(&glow.frame(empty.image()), "playfield_glow_trail");
+    }
+}
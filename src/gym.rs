@@ -0,0 +1,248 @@
+//! The game as a reinforcement learning environment, stepped one tick at a time without a window
+
+use macroquad::{prelude::*, rand};
+
+use crate::{
+    wrapped_offset, Game, ShipInput, BULLET_SIZE, BULLET_SPEED, ENERGY_SHIELD_MAX_ENERGY,
+    LARGE_ASTEROID_SIZE, SHIP_COLLISION_RADIUS, SHIP_HYPERSPACE_FREQUENCY, SHIP_MAX_SPEED,
+};
+
+pub const ENV_TICK: f32 = 1.0 / 60.0;
+pub const ENV_PLAYFIELD: Vec2 = Vec2::new(800.0, 600.0);
+// episodes are cut short after ten minutes of play
+pub const ENV_MAX_STEPS: u32 = 60 * 60 * 10;
+// how many of the closest objects of each kind are observed
+pub const OBS_ASTEROIDS: usize = 8;
+pub const OBS_SAUCERS: usize = 2;
+pub const OBS_BULLETS: usize = 4;
+const OBS_SHIP_LEN: usize = 9;
+// presence, offset, velocity and radius
+const OBS_OBJECT_LEN: usize = 6;
+pub const OBS_LEN: usize =
+    OBS_SHIP_LEN + (OBS_ASTEROIDS + OBS_SAUCERS + OBS_BULLETS) * OBS_OBJECT_LEN;
+
+/// What the agent sees after each step
+pub struct Observation {
+    /// Ship state followed by the nearest asteroids, saucers and saucer bullets, closest first
+    pub vector: [f32; OBS_LEN],
+    /// Greyscale frame, row by row, when the environment was asked to rasterise
+    pub frame: Option<Vec<u8>>,
+}
+
+/// Single player game stepped by an agent. Macroquad's random numbers are global, so only one
+/// environment should be stepped at a time in a process
+pub struct Env {
+    game: Game,
+    seed: u64,
+    steps: u32,
+    // width and height of the rasterised frame, none to skip it
+    raster: Option<(usize, usize)>,
+}
+
+impl Env {
+    /// Panics if the raster is given without a width or height
+    pub fn new(raster: Option<(usize, usize)>) -> Self {
+        assert!(
+            raster.is_none_or(|(width, height)| width > 0 && height > 0),
+            "the raster needs a width and height"
+        );
+        Self {
+            game: Game::new(1, ENV_PLAYFIELD),
+            seed: 0,
+            steps: 0,
+            raster,
+        }
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        rand::srand(seed);
+        self.game = Game::new(1, ENV_PLAYFIELD);
+        self.seed = seed;
+        self.steps = 0;
+        self.observe()
+    }
+
+    /// Advances one tick with the action, a bit set of left, right, thrust, fire, hyperspace and
+    /// shield from the lowest bit up. The reward is the score earned during the tick
+    pub fn step(&mut self, action: u8) -> (Observation, f32, bool) {
+        let score = self.game.players[0].score;
+        // reseeding each step keeps episodes reproducible whatever else uses the generator
+        rand::srand(self.seed ^ ((self.steps as u64) << 16));
+        self.game.update(ENV_TICK, &[ShipInput::from_bits(action)]);
+        self.game.take_events();
+        self.steps += 1;
+
+        let reward = (self.game.players[0].score - score) as f32;
+        let done = self.game.game_over || self.steps >= ENV_MAX_STEPS;
+        (self.observe(), reward, done)
+    }
+
+    fn observe(&self) -> Observation {
+        let game = &self.game;
+        let player = &game.players[0];
+        let ship = &player.ship;
+        let mut vector = [0.0; OBS_LEN];
+
+        vector[..OBS_SHIP_LEN].copy_from_slice(&[
+            ship.pos.x / game.bounds.x,
+            ship.pos.y / game.bounds.y,
+            ship.vel.x / SHIP_MAX_SPEED,
+            ship.vel.y / SHIP_MAX_SPEED,
+            ship.rot.sin(),
+            ship.rot.cos(),
+            (ship.state.is_destroyed() || ship.state.is_translating()) as u8 as f32,
+            (player.last_hyperspace / SHIP_HYPERSPACE_FREQUENCY).min(1.0),
+            ship.energy_shield_energy / ENERGY_SHIELD_MAX_ENERGY,
+        ]);
+
+        // objects of each kind fill their slots closest first, any left over stay zero
+        let mut slots = vector[OBS_SHIP_LEN..].chunks_exact_mut(OBS_OBJECT_LEN);
+        let mut observe_nearest = |objects: Vec<(Vec2, Vec2, f32)>, count: usize| {
+            let mut nearest: Vec<(Vec2, Vec2, f32)> = objects
+                .into_iter()
+                .map(|(pos, vel, radius)| (wrapped_offset(ship.pos, pos, game.bounds), vel, radius))
+                .collect();
+            nearest.sort_by(|a, b| a.0.length_squared().total_cmp(&b.0.length_squared()));
+            for (slot, (offset, vel, radius)) in slots.by_ref().take(count).zip(nearest) {
+                slot.copy_from_slice(&[
+                    1.0,
+                    offset.x / game.bounds.x,
+                    offset.y / game.bounds.y,
+                    (vel.x - ship.vel.x) / BULLET_SPEED,
+                    (vel.y - ship.vel.y) / BULLET_SPEED,
+                    radius / LARGE_ASTEROID_SIZE,
+                ]);
+            }
+        };
+        observe_nearest(
+            game.asteroids
+                .iter()
+                .map(|a| (a.pos, a.vel, a.size.size()))
+                .collect(),
+            OBS_ASTEROIDS,
+        );
+        observe_nearest(
+            game.saucers
+                .iter()
+                .map(|s| (s.pos, s.vel, s.size.size()))
+                .collect(),
+            OBS_SAUCERS,
+        );
+        observe_nearest(
+            game.saucer_bullets
+                .iter()
+                .map(|(b, _)| (b.pos, b.vel, BULLET_SIZE))
+                .collect(),
+            OBS_BULLETS,
+        );
+
+        Observation {
+            vector,
+            frame: self
+                .raster
+                .map(|(width, height)| self.rasterise(width, height)),
+        }
+    }
+
+    /// Draws everything as filled circles into a small greyscale image
+    fn rasterise(&self, width: usize, height: usize) -> Vec<u8> {
+        let game = &self.game;
+        let mut frame = vec![0; width * height];
+        let scale = Vec2::new(width as f32, height as f32) / game.bounds;
+        let mut plot = |pos: Vec2, radius: f32, shade: u8| {
+            let centre = pos * scale;
+            let r = (radius * scale.x).max(0.5);
+            let (x0, x1) = ((centre.x - r).floor() as i32, (centre.x + r).ceil() as i32);
+            let (y0, y1) = ((centre.y - r).floor() as i32, (centre.y + r).ceil() as i32);
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    let inside = Vec2::new(x as f32 + 0.5, y as f32 + 0.5).distance(centre) <= r;
+                    // objects wrap around the edges like they do in the game
+                    let (wx, wy) = (
+                        x.rem_euclid(width as i32) as usize,
+                        y.rem_euclid(height as i32) as usize,
+                    );
+                    if inside {
+                        frame[wy * width + wx] = frame[wy * width + wx].max(shade);
+                    }
+                }
+            }
+        };
+
+        game.asteroids
+            .iter()
+            .for_each(|a| plot(a.pos, a.size.size(), 96));
+        game.saucers
+            .iter()
+            .for_each(|s| plot(s.pos, s.size.size(), 160));
+        game.saucer_bullets
+            .iter()
+            .for_each(|(b, _)| plot(b.pos, BULLET_SIZE, 208));
+        let player = &game.players[0];
+        player
+            .bullets
+            .iter()
+            .for_each(|(b, _)| plot(b.pos, BULLET_SIZE, 128));
+        if !player.ship.state.is_destroyed() {
+            plot(player.ship.pos, SHIP_COLLISION_RADIUS, 255);
+        }
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollback::tests::SIMULATION;
+
+    /// Total reward and steps for an episode of the same actions over and over
+    fn run(env: &mut Env, seed: u64, action: u8) -> (f32, u32, [f32; OBS_LEN]) {
+        env.reset(seed);
+        let mut total = 0.0;
+        let mut steps = 0;
+        loop {
+            let (observation, reward, done) = env.step(action);
+            total += reward;
+            steps += 1;
+            if done {
+                return (total, steps, observation.vector);
+            }
+        }
+    }
+
+    #[test]
+    fn episodes_are_reproducible_from_the_seed() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut env = Env::new(None);
+        // spin and fire
+        let action = ShipInput {
+            right: true,
+            fire: true,
+            ..Default::default()
+        }
+        .to_bits();
+
+        let first = run(&mut env, 3, action);
+        let second = run(&mut env, 3, action);
+        assert_eq!(first, second);
+        assert!(first.0 > 0.0, "shooting should score something");
+    }
+
+    #[test]
+    fn rasterised_frame_shows_the_ship() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut env = Env::new(Some((80, 60)));
+        let observation = env.reset(1);
+        let frame = observation.frame.unwrap();
+        assert_eq!(frame.len(), 80 * 60);
+        // the ship starts in the middle of the playfield
+        assert_eq!(frame[30 * 80 + 40], 255);
+        assert_eq!(observation.vector[0], 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "the raster needs a width and height")]
+    fn empty_rasters_are_refused() {
+        Env::new(Some((0, 0)));
+    }
+}
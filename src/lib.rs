@@ -0,0 +1,3383 @@
+use std::f32::consts::{PI, TAU};
+
+use achievements::Achievements;
+use audio::Audio;
+use camera::CameraEffects;
+use console::Console;
+use events::{EventLog, GameEvent};
+use glow::Glow;
+use lives::{LifeEvent, Lives};
+use netplay::{NetSession, OnlineMode, RelayTransport};
+use pilot::pilot;
+use profiler::{Phase, PhaseTimes, Profiler};
+use render::{Frame, Renderer, Screen};
+use settings::Settings;
+use time_control::TimeControl;
+
+use macroquad::{
+    miniquad::date,
+    prelude::*,
+    rand::{self, gen_range},
+    ui::root_ui,
+};
+
+mod achievements;
+mod audio;
+mod camera;
+mod console;
+mod events;
+mod glow;
+pub mod gym;
+mod lives;
+mod mixer;
+mod netplay;
+mod palette;
+mod pilot;
+mod profiler;
+pub mod render;
+mod rollback;
+mod settings;
+pub mod simulate;
+mod storage;
+mod synth;
+mod time_control;
+
+const DEBUG: bool = false;
+// lines of past commands and replies the dev console shows
+const CONSOLE_LINES: usize = 10;
+const CONSOLE_COLOR: Color = GREEN;
+// length of a tick stepped through while paused
+const TIME_STEP: f32 = 1.0 / 60.0;
+// updates kept to rewind through, a few seconds' worth
+const TIME_REWIND_SNAPSHOTS: usize = 300;
+const TIME_SLOW_MOTION: [f32; 4] = [1.0, 0.5, 0.25, 0.1];
+// frames shown in the profiler's frame time graph
+const PROFILER_HISTORY: usize = 240;
+// how much each frame moves the profiler's averages, smoothing out the jitter
+const PROFILER_SMOOTHING: f64 = 0.05;
+
+const BACKGROUND_COLOR: Color = BLACK;
+const STAR_COLORS: [Color; 3] = [WHITE, LIGHTGRAY, GRAY];
+const STAR_NUM: usize = 600;
+const STAR_MAX_SIZE: f32 = 1.2;
+// most stars the settings allow, as a multiple of STAR_NUM
+const STAR_MAX_DENSITY: f32 = 2.0;
+// how hard the screen shakes unless the settings say otherwise
+const SCREEN_SHAKE: f32 = 1.0;
+// impacts add trauma to the camera, which shakes by the square of it as it wears off: at full
+// trauma and shake the view moves this far and turns this many degrees
+const CAMERA_SHAKE_OFFSET: f32 = 14.0;
+const CAMERA_SHAKE_ANGLE: f32 = 2.0;
+const CAMERA_SHAKE_FREQUENCY: f32 = 18.0;
+const CAMERA_TRAUMA_DECAY: f32 = 1.2;
+const CAMERA_TRAUMA_SHIP_HIT: f32 = 0.7;
+const CAMERA_TRAUMA_LARGE_EXPLOSION: f32 = 0.4;
+// seconds local play freezes for when a saucer is shot down
+const CAMERA_HIT_STOP: f32 = 0.08;
+// the view zooms in by this much and back out again on an extra life
+const CAMERA_ZOOM_PULSE: f32 = 0.04;
+const CAMERA_ZOOM_PULSE_TIME: f32 = 0.5;
+// vector monitor glow: how much of a frame's light is left the next frame, how bright and how
+// many pixels wide the bloom is, and how much darker every other scanline is
+const GLOW_PERSISTENCE: f32 = 0.75;
+const GLOW_BLOOM_STRENGTH: f32 = 1.2;
+const GLOW_BLOOM_SPREAD: f32 = 2.0;
+const GLOW_SCANLINE_DEPTH: f32 = 0.2;
+
+const INITIAL_LIVES: usize = 3;
+const HEART_VERTICIES: [Vec2; 8] = [
+    Vec2::new(0.0, -0.5),
+    Vec2::new(0.5, -1.0),
+    Vec2::new(1.0, -0.5),
+    Vec2::new(1.0, -0.25),
+    Vec2::new(0.0, 1.0),
+    Vec2::new(-1.0, -0.25),
+    Vec2::new(-1.0, -0.5),
+    Vec2::new(-0.5, -1.0),
+];
+const HEART_RADIUS: f32 = 16.0;
+const HEART_ANIMATION_TIME: f32 = 0.6;
+const LIFE_SCORE: usize = 10_000;
+
+const SHIP_COLOR: Color = SKYBLUE;
+const SHIP_WIDTH: f32 = 22.0;
+const SHIP_HEIGHT: f32 = 28.0;
+const SHIP_COLLISION_RADIUS: f32 = 10.0;
+const SHIP_ROTATION_SPEED: f32 = 0.4 * TAU;
+const SHIP_MAX_SPEED: f32 = 80.0;
+const SHIP_ACCELERATION: f32 = 200.0;
+const SHIP_DRAG: f32 = 0.02;
+const SHIP_HYPERSPACE_FREQUENCY: f32 = 2.0;
+const SHIP_HYPERSPACE_MIN_DISTANCE: f32 = 100.0;
+const SHIP_HYPERSPACE_SPEED: f32 = 300.0;
+const SHIP_HYPERSPACE_RULES: HyperspaceRules = HyperspaceRules::Modern;
+const ARCADE_HYPERSPACE_BASE_FAILURE: f32 = 0.05;
+const ARCADE_HYPERSPACE_FAILURE_PER_OBJECT: f32 = 0.015;
+const ARCADE_HYPERSPACE_MAX_FAILURE: f32 = 0.5;
+const SAFE_HYPERSPACE_GRID: usize = 12;
+const SHIP_SHIELD_TIME: f32 = 3.0;
+const SHIP_SHIELD_COLOR: Color = BLUE;
+const SHIP_DEATH_MODE: DeathMode = DeathMode::Respawn;
+const SHIP_RESPAWN_DELAY: f32 = 2.0;
+const SHIP_RESPAWN_CLEAR_RADIUS: f32 = 120.0;
+const SHIP_SPAWN_PROTECTION_TIME: f32 = 1.5;
+const SHIP_DEBRIS_TTL: f32 = 1.5;
+const SHIP_DEBRIS_SPEED: f32 = 30.0;
+const SHIP_DEBRIS_MAX_SPIN: f32 = TAU;
+const SHIP_ABILITY: ShipAbility = ShipAbility::HyperspaceAndShield;
+const SHIP_SPAWN_SPACING: f32 = 60.0;
+
+const MAX_PLAYERS: usize = 2;
+const PLAYER_COLORS: [Color; MAX_PLAYERS] = [SHIP_COLOR, PINK];
+const PLAYER_CONTROLS: [Controls; MAX_PLAYERS] = [
+    Controls {
+        left: KeyCode::A,
+        right: KeyCode::D,
+        thrust: KeyCode::W,
+        fire: KeyCode::Space,
+        hyperspace: KeyCode::LeftShift,
+        shield: KeyCode::S,
+    },
+    Controls {
+        left: KeyCode::Left,
+        right: KeyCode::Right,
+        thrust: KeyCode::Up,
+        fire: KeyCode::RightControl,
+        hyperspace: KeyCode::RightShift,
+        shield: KeyCode::Down,
+    },
+];
+const COOP_SHARED_LIVES: bool = false;
+const COOP_FRIENDLY_FIRE: bool = false;
+const ALTERNATING_INTERSTITIAL_TIME: f32 = 2.0;
+
+const ENERGY_SHIELD_RADIUS: f32 = SHIP_COLLISION_RADIUS * 2.0;
+const ENERGY_SHIELD_MAX_ENERGY: f32 = 1.0;
+const ENERGY_SHIELD_MIN_ACTIVATION_ENERGY: f32 = 0.25;
+const ENERGY_SHIELD_DRAIN: f32 = 0.4;
+const ENERGY_SHIELD_BOUNCE_COST: f32 = 0.1;
+const ENERGY_SHIELD_RECHARGE: f32 = 0.15;
+const ENERGY_SHIELD_RECHARGE_DELAY: f32 = 1.0;
+const ENERGY_SHIELD_COLOR: Color = GREEN;
+
+const SMALL_ASTEROID_SIZE: f32 = 12.0;
+const SMALL_ASTEROID_SPEED: f32 = 130.0;
+const SMALL_ASTEROID_SCORE: usize = 100;
+
+const MEDIUM_ASTEROID_SIZE: f32 = 20.0;
+const MEDIUM_ASTEROID_SPEED: f32 = 75.0;
+const MEDIUM_ASTEROID_SCORE: usize = 50;
+
+const LARGE_ASTEROID_SIZE: f32 = 40.0;
+const LARGE_ASTEROID_SPEED: f32 = 40.0;
+const LARGE_ASTEROID_SCORE: usize = 20;
+
+const ASTEROID_SPLIT_ANGLE: f32 = PI / 6.0;
+const ASTEROID_MIN_SPAWN_RATE: f32 = 0.5;
+const ASTEROID_INITIAL_MAX_SPAWN_RATE: f32 = 5.0;
+const ASTEROID_SPAWN_DECREASE_FACTOR: f32 = 0.001;
+
+const PARTICLE_SIZE: f32 = 5.0;
+
+const ASTEROID_PARTICLE_SPAWN: usize = 3;
+const ASTEROID_PARTICLE_TTL: f32 = 1.5;
+const ASTEROID_PARTICLE_COLOR: Color = MAROON;
+const ASTEROID_PARTICLE_SPEED: f32 = 40.0;
+
+const ASTEROID_COLOR: Color = DARKGRAY;
+const ASTEROID_MIN_VERTICIES: usize = 8;
+const ASTEROID_MAX_VERTICIES: usize = 12;
+const ASTEROID_MIN_RADIUS: f32 = 0.8;
+const ASTEROID_MAX_RADIUS: f32 = 1.1;
+
+const LARGE_SAUCER_SIZE: f32 = 25.0;
+const LARGE_SAUCER_SPEED: f32 = 32.0;
+const LARGE_SAUCER_SCORE: usize = 200;
+
+const SMALL_SAUCER_SIZE: f32 = 15.0;
+const SMALL_SAUCER_SPEED: f32 = 50.0;
+const SMALL_SAUCER_SCORE: usize = 1000;
+
+const SAUCER_SMALL_SCORE_THRESHOLD: usize = 10_000;
+const SAUCER_SMALL_MAX_PROBABILTY: f32 = 0.8;
+const SAUCER_COLOR: Color = DARKPURPLE;
+const SAUCER_SPAWN_RATE: f32 = 10.0;
+const SAUCER_MAX: usize = 3;
+const SAUCER_MAX_PER_WAVE: usize = 5;
+const SAUCER_BULLET_FREQUENCY: f32 = 2.0;
+const SAUCER_BULLET_COLOR: Color = PURPLE;
+const SAUCER_BULLET_TTL: f32 = 3.0;
+const SAUCER_VERTICIES: [Vec2; 10] = [
+    Vec2::new(1.1, 0.2),
+    Vec2::new(0.4, 0.7),
+    Vec2::new(-0.4, 0.7),
+    Vec2::new(-1.1, 0.2),
+    Vec2::new(-0.3, -0.2),
+    Vec2::new(-0.2, -0.7),
+    Vec2::new(0.2, -0.7),
+    Vec2::new(0.3, -0.2),
+    Vec2::new(1.1, 0.2),
+    Vec2::new(-1.1, 0.2),
+];
+
+const SAUCER_PARTICLE_SPAWN: usize = 3;
+const SAUCER_PARTICLE_TTL: f32 = 1.5;
+const SAUCER_PARTICLE_COLOR: Color = DARKPURPLE;
+const SAUCER_PARTICLE_SPEED: f32 = 40.0;
+
+const BULLET_COLOR: Color = LIME;
+const BULLET_SIZE: f32 = 5.0;
+const BULLET_SPEED: f32 = 150.0;
+const BULLET_FREQUENCY: f32 = 0.2;
+const MAX_BULLETS: usize = 4;
+const BULLET_TTL: f32 = 3.0;
+
+const COMBO_WINDOW: f32 = 2.0;
+const COMBO_HITS_PER_MULTIPLIER: usize = 4;
+const COMBO_MAX_MULTIPLIER: usize = 5;
+const COMBO_COLOR: Color = GOLD;
+
+const WAVE_QUICK_CLEAR_TIME: f32 = 45.0;
+const WAVE_QUICK_CLEAR_BONUS: usize = 500;
+const WAVE_NO_DEATH_BONUS: usize = 1000;
+
+const SCORE_TEXT_TTL: f32 = 1.0;
+const SCORE_TEXT_SPEED: f32 = 30.0;
+const SCORE_TEXT_SIZE: f32 = 20.0;
+
+const MASTER_VOLUME: f32 = 0.8;
+const SFX_VOLUME: f32 = 0.6;
+const MUSIC_VOLUME: f32 = 0.5;
+// seconds between heartbeats at the start of a wave, and once it is at its most frantic
+const MUSIC_SLOWEST_BEAT: f32 = 1.0;
+const MUSIC_FASTEST_BEAT: f32 = 0.25;
+// how many asteroids left on screen count as thinned out, the fewer the faster the beat
+const MUSIC_THINNING: f32 = 4.0;
+const MUSIC_WAVE_SPEEDUP: f32 = 0.05;
+// how far sounds are panned at the edges of the playfield, and how much quieter they are there
+const STEREO_WIDTH: f32 = 0.8;
+const STEREO_EDGE_ATTENUATION: f32 = 0.3;
+// positions across the stereo field each sound is rendered at
+const STEREO_PAN_STEPS: usize = 5;
+// how much higher an approaching saucer sounds, and lower a departing one
+const DOPPLER_SHIFT: f32 = 0.06;
+
+const ACHIEVEMENT_TOAST_TIME: f32 = 4.0;
+const ACHIEVEMENT_COLOR: Color = GOLD;
+
+const DEATHMATCH_KILL_LIMIT: usize = 10;
+const DEATHMATCH_TIME_LIMIT: f32 = 180.0;
+const DEATHMATCH_KILL_SCORE: usize = 1000;
+
+// seconds without any input on the title screen before the demo game starts
+const ATTRACT_IDLE_TIME: f32 = 5.0;
+
+// online games run on a fixed playfield and tick so every peer simulates the same thing
+const RELAY_URL: &str = "ws://127.0.0.1:9001";
+const NET_PLAYFIELD: Vec2 = Vec2::new(800.0, 600.0);
+const NET_TICK: f32 = 1.0 / 60.0;
+// ticks between reading an input and it being simulated, hiding the round trip to the other peer
+const NET_INPUT_DELAY: u32 = 4;
+const NET_MAX_TICKS_PER_FRAME: u32 = 8;
+// how many ticks the game can run ahead on predicted inputs before waiting for the real ones
+const NET_MAX_ROLLBACK: u32 = 8;
+// most inputs sent in one message when catching up a peer that has missed some
+const NET_MAX_INPUT_BATCH: u32 = 32;
+const NET_CHECKSUM_INTERVAL: u32 = 60;
+
+#[derive(Default, Clone)]
+struct Translation {
+    from: Vec2,
+    to: Vec2,
+    duration: f32,
+    current_time: f32,
+}
+
+impl Translation {
+    fn get(&self) -> Vec2 {
+        self.from + (self.to - self.from) * (self.current_time / self.duration)
+    }
+}
+
+#[derive(Clone)]
+enum ShipState {
+    Normal,
+    Hyperdrive,
+    Shielded,
+    Destroyed,
+}
+
+impl ShipState {
+    fn is_translating(&self) -> bool {
+        matches!(self, Self::Hyperdrive)
+    }
+
+    fn is_destroyed(&self) -> bool {
+        matches!(self, Self::Destroyed)
+    }
+
+    fn is_invincible(&self) -> bool {
+        !matches!(self, Self::Normal)
+    }
+}
+
+/// What happens to the ship when it is hit
+#[allow(dead_code)]
+#[derive(Default, Clone, Copy)]
+enum DeathMode {
+    // Ship explodes and respawns in the centre once it is clear
+    #[default]
+    Respawn,
+    // Ship keeps flying with a temporary shield
+    Casual,
+}
+
+/// Which abilities the player can activate, and on which keys
+#[allow(dead_code)]
+#[derive(Default, Clone, Copy)]
+enum ShipAbility {
+    Hyperspace,
+    // Replaces hyperspace, activated with the hyperspace key
+    Shield,
+    // Shield is activated with a separate key
+    #[default]
+    HyperspaceAndShield,
+}
+
+impl ShipAbility {
+    fn has_hyperspace(&self) -> bool {
+        !matches!(self, Self::Shield)
+    }
+
+    fn has_shield(&self) -> bool {
+        !matches!(self, Self::Hyperspace)
+    }
+}
+
+/// Keys used to fly a ship
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Controls {
+    left: KeyCode,
+    right: KeyCode,
+    thrust: KeyCode,
+    fire: KeyCode,
+    hyperspace: KeyCode,
+    shield: KeyCode,
+}
+
+impl Controls {
+    /// Names the keys are saved and shown under, in the order of keys
+    const ACTIONS: [&'static str; 6] = ["left", "right", "thrust", "fire", "hyperspace", "shield"];
+
+    fn keys(&self) -> [KeyCode; 6] {
+        [
+            self.left,
+            self.right,
+            self.thrust,
+            self.fire,
+            self.hyperspace,
+            self.shield,
+        ]
+    }
+
+    fn key_mut(&mut self, action: usize) -> &mut KeyCode {
+        match action {
+            0 => &mut self.left,
+            1 => &mut self.right,
+            2 => &mut self.thrust,
+            3 => &mut self.fire,
+            4 => &mut self.hyperspace,
+            _ => &mut self.shield,
+        }
+    }
+
+    fn read(&self, ability: ShipAbility) -> ShipInput {
+        self.read_with(ability, is_key_pressed, is_key_down)
+    }
+
+    /// What the keys ask of a ship with the given abilities, from whether each key has just
+    /// been pressed and whether it is held down
+    fn read_with(
+        &self,
+        ability: ShipAbility,
+        pressed: impl Fn(KeyCode) -> bool,
+        down: impl Fn(KeyCode) -> bool,
+    ) -> ShipInput {
+        ShipInput {
+            left: down(self.left),
+            right: down(self.right),
+            thrust: down(self.thrust),
+            fire: down(self.fire),
+            hyperspace: ability.has_hyperspace() && pressed(self.hyperspace),
+            shield: match ability {
+                ShipAbility::Hyperspace => false,
+                ShipAbility::Shield => down(self.hyperspace),
+                ShipAbility::HyperspaceAndShield => down(self.shield),
+            },
+        }
+    }
+}
+
+/// What a player wants their ship to do this frame
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+struct ShipInput {
+    left: bool,
+    right: bool,
+    thrust: bool,
+    fire: bool,
+    // hyperspace triggers once per press, the rest are held
+    hyperspace: bool,
+    shield: bool,
+}
+
+impl ShipInput {
+    /// Packs the input into a byte for sending over the network
+    fn to_bits(self) -> u8 {
+        [
+            self.left,
+            self.right,
+            self.thrust,
+            self.fire,
+            self.hyperspace,
+            self.shield,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, &held)| bits | (held as u8) << i)
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        let held = |i: u8| bits & (1 << i) != 0;
+        Self {
+            left: held(0),
+            right: held(1),
+            thrust: held(2),
+            fire: held(3),
+            hyperspace: held(4),
+            shield: held(5),
+        }
+    }
+}
+
+/// How the hyperspace destination is chosen and whether the jump can go wrong
+#[allow(dead_code)]
+#[derive(Default, Clone, Copy)]
+enum HyperspaceRules {
+    // Random destination a minimum distance away, always survived
+    #[default]
+    Modern,
+    // Random destination anywhere, possibly inside an asteroid, with a chance of
+    // self-destruction that grows with the number of objects on screen
+    Arcade,
+    // Destination is the position farthest from any threat
+    Safe,
+}
+
+#[derive(Clone)]
+struct Ship {
+    pos: Vec2,
+    rot: f32,
+    vel: Vec2,
+    acc: Vec2,
+    color: Color,
+    state: ShipState,
+    current_translation: Translation,
+    // whether the current hyperspace jump will destroy the ship on arrival
+    hyperspace_malfunction: bool,
+    shield_time: f32,
+    shield_duration: f32,
+    respawn_time: f32,
+    energy_shield_active: bool,
+    energy_shield_energy: f32,
+    // time since the energy shield was last active, used to delay recharging
+    energy_shield_idle_time: f32,
+    // the engine fired this update
+    thrusting: bool,
+}
+
+impl Default for Ship {
+    fn default() -> Self {
+        Self {
+            pos: Vec2::ZERO,
+            rot: 0.0,
+            vel: Vec2::ZERO,
+            acc: Vec2::ZERO,
+            color: SHIP_COLOR,
+            state: ShipState::Normal,
+            current_translation: Translation {
+                ..Default::default()
+            },
+            hyperspace_malfunction: false,
+            shield_time: 0.0,
+            shield_duration: SHIP_SHIELD_TIME,
+            respawn_time: 0.0,
+            energy_shield_active: false,
+            energy_shield_energy: ENERGY_SHIELD_MAX_ENERGY,
+            energy_shield_idle_time: 0.0,
+            thrusting: false,
+        }
+    }
+}
+
+impl Ship {
+    fn get_tri(&self) -> (Vec2, Vec2, Vec2) {
+        (
+            // Ship Nose
+            Vec2::new(
+                self.pos.x + self.rot.sin() * (SHIP_HEIGHT - SHIP_COLLISION_RADIUS),
+                self.pos.y - self.rot.cos() * (SHIP_HEIGHT - SHIP_COLLISION_RADIUS),
+            ),
+            // Left Base
+            Vec2::new(
+                self.pos.x
+                    - self.rot.cos() * SHIP_WIDTH / 2.
+                    - self.rot.sin() * SHIP_COLLISION_RADIUS,
+                self.pos.y - self.rot.sin() * SHIP_WIDTH / 2.
+                    + self.rot.cos() * SHIP_COLLISION_RADIUS,
+            ),
+            // Right Base
+            Vec2::new(
+                self.pos.x + self.rot.cos() * SHIP_WIDTH / 2.
+                    - self.rot.sin() * SHIP_COLLISION_RADIUS,
+                self.pos.y
+                    + self.rot.sin() * SHIP_WIDTH / 2.
+                    + self.rot.cos() * SHIP_COLLISION_RADIUS,
+            ),
+        )
+    }
+
+    fn get_unit_direction(&self) -> Vec2 {
+        Vec2::new(self.rot.sin(), -self.rot.cos())
+    }
+
+    /// Splits the hull into line segments flying away from the ship
+    fn break_apart(&self) -> Vec<Debris> {
+        let (v1, v2, v3) = self.get_tri();
+        [(v1, v2), (v2, v3), (v3, v1)]
+            .into_iter()
+            .map(|(from, to)| {
+                let centre = (from + to) / 2.0;
+                Debris {
+                    pos: centre,
+                    vel: self.vel
+                        + (centre - self.pos).normalize_or_zero() * SHIP_DEBRIS_SPEED
+                        + random_unit_vector() * SHIP_DEBRIS_SPEED * 0.5,
+                    half_length: (to - from) / 2.0,
+                    rot: 0.0,
+                    spin: rand::gen_range(-SHIP_DEBRIS_MAX_SPIN, SHIP_DEBRIS_MAX_SPIN),
+                    color: Color {
+                        a: 1.0,
+                        ..self.color
+                    },
+                    ttl: SHIP_DEBRIS_TTL,
+                    time: 0.0,
+                }
+            })
+            .collect()
+    }
+
+    fn is_vulnerable(&self) -> bool {
+        !self.state.is_invincible() && !self.energy_shield_active
+    }
+
+    /// Bounces an object of the given radius off the energy shield, returning whether it hit.
+    /// The object keeps its speed and is pushed outside of the shield.
+    fn deflect(&mut self, pos: &mut Vec2, vel: &mut Vec2, radius: f32) -> bool {
+        let offset = *pos - self.pos;
+        if !self.energy_shield_active || offset.length() > ENERGY_SHIELD_RADIUS + radius {
+            return false;
+        }
+
+        let normal = offset.try_normalize().unwrap_or(-self.get_unit_direction());
+        if vel.dot(normal) < 0.0 {
+            *vel -= 2.0 * vel.dot(normal) * normal;
+        }
+        *pos = self.pos + normal * (ENERGY_SHIELD_RADIUS + radius);
+
+        self.energy_shield_energy =
+            (self.energy_shield_energy - ENERGY_SHIELD_BOUNCE_COST).max(0.0);
+        true
+    }
+}
+
+#[derive(Clone)]
+struct Bullet {
+    pos: Vec2,
+    vel: Vec2,
+    collided: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AsteroidType {
+    Small,
+    Medium,
+    Large,
+}
+
+impl AsteroidType {
+    fn size(&self) -> f32 {
+        match self {
+            AsteroidType::Small => SMALL_ASTEROID_SIZE,
+            AsteroidType::Medium => MEDIUM_ASTEROID_SIZE,
+            AsteroidType::Large => LARGE_ASTEROID_SIZE,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        match self {
+            AsteroidType::Small => SMALL_ASTEROID_SPEED,
+            AsteroidType::Medium => MEDIUM_ASTEROID_SPEED,
+            AsteroidType::Large => LARGE_ASTEROID_SPEED,
+        }
+    }
+    fn score(&self) -> usize {
+        match self {
+            AsteroidType::Small => SMALL_ASTEROID_SCORE,
+            AsteroidType::Medium => MEDIUM_ASTEROID_SCORE,
+            AsteroidType::Large => LARGE_ASTEROID_SCORE,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            AsteroidType::Small => "small",
+            AsteroidType::Medium => "medium",
+            AsteroidType::Large => "large",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Asteroid {
+    size: AsteroidType,
+    pos: Vec2,
+    vel: Vec2,
+    collided: bool,
+    // player credited with destroying it
+    hit_by: Option<usize>,
+    verticies: Vec<Vec2>,
+}
+
+impl Asteroid {
+    fn new(size: AsteroidType, pos: Vec2, vel: Vec2) -> Self {
+        Self {
+            size,
+            pos,
+            vel,
+            collided: false,
+            hit_by: None,
+            verticies: generate_asteroid_vertices(),
+        }
+    }
+
+    fn split(&self) -> Option<Vec<Asteroid>> {
+        if let AsteroidType::Small = self.size {
+            return None;
+        };
+
+        let vel = self.vel.normalize();
+
+        let split_velocities = vec![
+            Vec2::new(
+                vel.x * ASTEROID_SPLIT_ANGLE.cos() - vel.y * ASTEROID_SPLIT_ANGLE.sin(),
+                vel.x * ASTEROID_SPLIT_ANGLE.sin() + vel.y * ASTEROID_SPLIT_ANGLE.cos(),
+            ),
+            Vec2::new(
+                vel.x * ASTEROID_SPLIT_ANGLE.cos() + vel.y * ASTEROID_SPLIT_ANGLE.sin(),
+                -vel.x * ASTEROID_SPLIT_ANGLE.sin() + vel.y * ASTEROID_SPLIT_ANGLE.cos(),
+            ),
+        ];
+
+        match self.size {
+            AsteroidType::Large => Some(
+                split_velocities
+                    .into_iter()
+                    .map(|v| {
+                        Asteroid::new(
+                            AsteroidType::Medium,
+                            self.pos,
+                            v * AsteroidType::Medium.speed(),
+                        )
+                    })
+                    .collect(),
+            ),
+            AsteroidType::Medium => Some(
+                split_velocities
+                    .into_iter()
+                    .map(|v| {
+                        Asteroid::new(
+                            AsteroidType::Small,
+                            self.pos,
+                            v * AsteroidType::Small.speed(),
+                        )
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+fn generate_asteroid_vertices() -> Vec<Vec2> {
+    let num_vertices = rand::gen_range(ASTEROID_MIN_VERTICIES, ASTEROID_MAX_VERTICIES);
+    (0..num_vertices)
+        .map(|v| {
+            let a = rand::gen_range(
+                (v as f32) * TAU / (num_vertices as f32),
+                ((v + 1) as f32) * TAU / (num_vertices as f32),
+            );
+            let r = rand::gen_range(ASTEROID_MIN_RADIUS, ASTEROID_MAX_RADIUS);
+            Vec2::new(r * a.cos(), r * a.sin())
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SaucerSize {
+    Large,
+    Small,
+}
+
+impl SaucerSize {
+    fn from_score(score: usize) -> Self {
+        let prob = if score >= SAUCER_SMALL_SCORE_THRESHOLD {
+            SAUCER_SMALL_MAX_PROBABILTY
+        } else {
+            SAUCER_SMALL_MAX_PROBABILTY * (score as f32) / (SAUCER_SMALL_SCORE_THRESHOLD as f32)
+        };
+        if rand::gen_range(0.0, 1.0) < prob {
+            Self::Small
+        } else {
+            Self::Large
+        }
+    }
+
+    fn size(&self) -> f32 {
+        match self {
+            Self::Large => LARGE_SAUCER_SIZE,
+            Self::Small => SMALL_SAUCER_SIZE,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        match self {
+            Self::Large => LARGE_SAUCER_SPEED,
+            Self::Small => SMALL_SAUCER_SPEED,
+        }
+    }
+
+    fn score(&self) -> usize {
+        match self {
+            Self::Large => LARGE_SAUCER_SCORE,
+            Self::Small => SMALL_SAUCER_SCORE,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Large => "large",
+            Self::Small => "small",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Saucer {
+    size: SaucerSize,
+    pos: Vec2,
+    vel: Vec2,
+    last_shot: f32,
+    collided: bool,
+    // player credited with destroying it, and how they did it
+    hit_by: Option<(usize, Kill)>,
+    // bullets each player has had in the air while it has been around
+    shots: Vec<usize>,
+}
+
+impl Saucer {
+    fn new(size: SaucerSize, bounds: Vec2) -> Saucer {
+        let speed = size.speed();
+        Self {
+            size,
+            pos: random_edge_position(bounds),
+            vel: random_unit_vector() * speed,
+            last_shot: 0.0,
+            collided: false,
+            hit_by: None,
+            shots: Vec::new(),
+        }
+    }
+
+    fn shoot(&self, target: Option<&Ship>) -> Bullet {
+        match (&self.size, target) {
+            (SaucerSize::Large, _) | (_, None) => Bullet {
+                pos: self.pos,
+                vel: random_unit_vector() * BULLET_SPEED,
+                collided: false,
+            },
+            (SaucerSize::Small, Some(ship)) => {
+                let prediction_offset = ship.get_unit_direction() * ship.vel.length();
+                let target = (ship.pos + prediction_offset - self.pos).normalize();
+
+                Bullet {
+                    pos: self.pos,
+                    vel: target * BULLET_SPEED,
+                    collided: false,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Particle {
+    color: Color,
+    ttl: f32,
+    time: f32,
+    pos: Vec2,
+    vel: Vec2,
+    size: f32,
+}
+
+/// A spinning line segment left behind when the ship is destroyed
+#[derive(Clone)]
+struct Debris {
+    pos: Vec2,
+    vel: Vec2,
+    // offset from the centre to one end of the segment, before rotation
+    half_length: Vec2,
+    rot: f32,
+    spin: f32,
+    color: Color,
+    ttl: f32,
+    time: f32,
+}
+
+/// Points earned, floating up from where they were scored
+#[derive(Clone)]
+struct ScoreText {
+    text: String,
+    color: Color,
+    ttl: f32,
+    time: f32,
+    pos: Vec2,
+}
+
+fn wrap_position(pos: &mut Vec2, bounds: Vec2) {
+    if pos.x < 0.0 {
+        pos.x = bounds.x;
+    } else if pos.x > bounds.x {
+        pos.x = 0.0;
+    }
+    if pos.y < 0.0 {
+        pos.y = bounds.y;
+    } else if pos.y > bounds.y {
+        pos.y = 0.0;
+    }
+}
+
+fn draw_centered_text(text: &str, x: f32, y: f32, font_size: f32, color: Color) {
+    let text_center = get_text_center(text, None, font_size as u16, 1.0, 0.0);
+    draw_text(text, x - text_center.x, y - text_center.y, font_size, color);
+}
+
+fn random_unit_vector() -> Vec2 {
+    let rot = rand::gen_range(0.0, TAU);
+    Vec2::new(rot.cos(), rot.sin())
+}
+
+fn random_position(bounds: Vec2) -> Vec2 {
+    Vec2::new(rand::gen_range(0.0, bounds.x), gen_range(0.0, bounds.y))
+}
+
+/// Shortest vector from one point to another, which may cross the edges of the playfield
+fn wrapped_offset(from: Vec2, to: Vec2, bounds: Vec2) -> Vec2 {
+    let wrap = |d: f32, size: f32| d - size * (d / size).round();
+    let d = to - from;
+    Vec2::new(wrap(d.x, bounds.x), wrap(d.y, bounds.y))
+}
+
+/// Distance between two points, taking screen wrapping into account
+fn wrapped_distance(a: Vec2, b: Vec2, bounds: Vec2) -> f32 {
+    wrapped_offset(a, b, bounds).length()
+}
+
+fn random_edge_position(bounds: Vec2) -> Vec2 {
+    let side: i32 = rand::gen_range(0, 4);
+
+    match side {
+        // TOP
+        0 => Vec2::new(rand::gen_range(0.0, bounds.x), 0.0),
+        // RIGHT
+        1 => Vec2::new(bounds.x, rand::gen_range(0.0, bounds.y)),
+        // BOTTOM
+        2 => Vec2::new(rand::gen_range(0.0, bounds.x), bounds.y),
+        // LEFT
+        _ => Vec2::new(0.0, rand::gen_range(0.0, bounds.y)),
+    }
+}
+
+/// Camera showing the whole playfield as large as fits in the window, letterboxed
+fn playfield_camera(bounds: Vec2) -> Camera2D {
+    let scale = (screen_width() / bounds.x).min(screen_height() / bounds.y);
+    let view = Vec2::new(screen_width(), screen_height()) / scale;
+    Camera2D::from_display_rect(Rect::new(
+        (bounds.x - view.x) / 2.0,
+        (bounds.y - view.y) / 2.0,
+        view.x,
+        view.y,
+    ))
+}
+
+fn draw_asteroid(frame: &mut Frame, a: &Asteroid) {
+    let points: Vec<Vec2> = a
+        .verticies
+        .iter()
+        .map(|v| a.pos + a.size.size() * *v)
+        .collect();
+    frame.outline(&points, 2.0, ASTEROID_COLOR);
+}
+
+fn draw_saucer(frame: &mut Frame, s: &Saucer) {
+    let points = SAUCER_VERTICIES.map(|v| s.pos + s.size.size() * v);
+    frame.outline(&points, 2.0, SAUCER_COLOR);
+}
+
+fn draw_ability_bar(frame: &mut Frame, x: f32, bottom: f32, fill: f32, color: Color, label: &str) {
+    let height = 30.0 * fill;
+    frame.rectangle(x - 10.0, bottom - 20.0 - height, 20.0, height, color);
+    frame.centered_text(label, Vec2::new(x, bottom - 10.0), 16.0, WHITE);
+}
+
+fn draw_heart(frame: &mut Frame, p: Vec2, scale: f32, color: Color) {
+    let radius = HEART_RADIUS * scale;
+    let points = HEART_VERTICIES.map(|v| p + radius * v);
+    frame.outline(&points, radius / 6.0, color);
+}
+
+#[derive(Clone)]
+struct Player {
+    ship: Ship,
+    color: Color,
+    lives: Lives,
+    // most recent change to lives and how long it has been animating
+    heart_animation: Option<(LifeEvent, f32)>,
+    // the ship was destroyed with no lives left, so it won't respawn
+    out_of_lives: bool,
+
+    // bullet, time alive
+    bullets: Vec<(Bullet, f32)>,
+    last_bullet: f32,
+    last_hyperspace: f32,
+
+    score: usize,
+    // consecutive kills, and time since the last one
+    combo: usize,
+    combo_time: f32,
+    // other ships shot down in a deathmatch
+    kills: usize,
+}
+
+impl Player {
+    fn new(color: Color, pos: Vec2) -> Self {
+        Self {
+            ship: Ship {
+                pos,
+                color,
+                ..Default::default()
+            },
+            color,
+            lives: Lives::default(),
+            heart_animation: None,
+            out_of_lives: false,
+            bullets: Vec::new(),
+            last_bullet: 0.0,
+            last_hyperspace: 0.0,
+            score: 0,
+            combo: 0,
+            combo_time: 0.0,
+            kills: 0,
+        }
+    }
+
+    fn combo_multiplier(&self) -> usize {
+        (1 + self.combo / COMBO_HITS_PER_MULTIPLIER).min(COMBO_MAX_MULTIPLIER)
+    }
+}
+
+/// Limits ending a deathmatch, whichever is reached first
+#[derive(Clone, Copy)]
+struct MatchRules {
+    kill_limit: usize,
+    time_limit: f32,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            kill_limit: DEATHMATCH_KILL_LIMIT,
+            time_limit: DEATHMATCH_TIME_LIMIT,
+        }
+    }
+}
+
+/// What destroyed a ship
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeathCause {
+    Asteroid(AsteroidType),
+    Saucer,
+    SaucerBullet,
+    // another player's bullet
+    PlayerBullet(usize),
+    // a failed hyperspace jump
+    Hyperspace,
+}
+
+/// How a player brought a saucer down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kill {
+    // with the number of bullets they had in the air while it was around
+    Shot { shots: usize },
+    Rammed,
+}
+
+impl Kill {
+    fn name(&self) -> &'static str {
+        match self {
+            Kill::Shot { .. } => "shot",
+            Kill::Rammed => "rammed",
+        }
+    }
+}
+
+impl DeathCause {
+    fn name(&self) -> &'static str {
+        match self {
+            DeathCause::Asteroid(AsteroidType::Small) => "small asteroid",
+            DeathCause::Asteroid(AsteroidType::Medium) => "medium asteroid",
+            DeathCause::Asteroid(AsteroidType::Large) => "large asteroid",
+            DeathCause::Saucer => "saucer",
+            DeathCause::SaucerBullet => "saucer bullet",
+            DeathCause::PlayerBullet(_) => "player bullet",
+            DeathCause::Hyperspace => "hyperspace",
+        }
+    }
+}
+
+/// Values that shape the difficulty, which the simulator varies to see how the game balances
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tuning {
+    /// Seconds between saucers
+    pub saucer_spawn_rate: f32,
+    /// How quickly asteroids start arriving faster over a wave
+    pub asteroid_spawn_decrease_factor: f32,
+    pub ship_max_speed: f32,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            saucer_spawn_rate: SAUCER_SPAWN_RATE,
+            asteroid_spawn_decrease_factor: ASTEROID_SPAWN_DECREASE_FACTOR,
+            ship_max_speed: SHIP_MAX_SPEED,
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+struct Game {
+    // size of the playfield, everything wraps around its edges
+    bounds: Vec2,
+    players: Vec<Player>,
+    // all players draw from the first player's lives
+    shared_lives: bool,
+    friendly_fire: bool,
+    hyperspace_rules: HyperspaceRules,
+    death_mode: DeathMode,
+    ship_ability: ShipAbility,
+    // seconds new player bullets are moved ahead, making up for delayed network input
+    bullet_lead: f32,
+    // ships fight each other until the rules end the match, lives are unlimited
+    deathmatch: Option<MatchRules>,
+    match_time: f32,
+    tuning: Tuning,
+
+    asteroids: Vec<Asteroid>,
+    asteroid_wave: usize,
+    asteroids_spawned_in_wave: usize,
+    last_asteroid: f32,
+    max_asteroid_spawn_rate: f32,
+    next_asteroid_spawn_rate: f32,
+
+    saucers: Vec<Saucer>,
+    last_saucer: f32,
+    saucers_spawned_in_wave: usize,
+
+    // bullet, time alive
+    saucer_bullets: Vec<(Bullet, f32)>,
+
+    game_over: bool,
+    wave_time: f32,
+    wave_deaths: usize,
+
+    particles: Vec<Particle>,
+    debris: Vec<Debris>,
+    score_texts: Vec<ScoreText>,
+
+    frame: usize,
+    // what has happened since they were last taken
+    events: Vec<GameEvent>,
+
+    // ships come through collisions unharmed
+    god: bool,
+    // changed from the dev console or rewound, so nothing earned in it counts
+    cheated: bool,
+    // where the last update spent its time
+    profile: PhaseTimes,
+}
+
+impl Game {
+    fn new(player_count: usize, bounds: Vec2) -> Self {
+        let mut game = Game {
+            bounds,
+            shared_lives: COOP_SHARED_LIVES,
+            friendly_fire: COOP_FRIENDLY_FIRE,
+            hyperspace_rules: SHIP_HYPERSPACE_RULES,
+            death_mode: SHIP_DEATH_MODE,
+            ship_ability: SHIP_ABILITY,
+            max_asteroid_spawn_rate: ASTEROID_INITIAL_MAX_SPAWN_RATE,
+            tuning: Tuning::default(),
+            events: vec![GameEvent::WaveStarted { wave: 1 }],
+            ..Default::default()
+        };
+        game.players = (0..player_count.min(MAX_PLAYERS))
+            .map(|i| Player::new(PLAYER_COLORS[i], game.spawn_position(i, player_count)))
+            .collect();
+        game
+    }
+
+    fn new_deathmatch(player_count: usize, bounds: Vec2) -> Self {
+        Self {
+            friendly_fire: true,
+            deathmatch: Some(MatchRules::default()),
+            ..Self::new(player_count, bounds)
+        }
+    }
+
+    /// The player with the most kills, none if the lead is shared
+    fn match_winner(&self) -> Option<usize> {
+        let most = self.players.iter().map(|p| p.kills).max()?;
+        let mut leaders = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.kills == most);
+        match (leaders.next(), leaders.next()) {
+            (Some((i, _)), None) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Everything that has happened since the last time they were taken
+    fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn get_wave_asteroid_amount(&self) -> usize {
+        self.asteroid_wave * 2 + 2
+    }
+
+    fn total_score(&self) -> usize {
+        self.players.iter().map(|p| p.score).sum()
+    }
+
+    /// Index of the player whose lives are used by the given player
+    fn lives_owner(&self, player: usize) -> usize {
+        if self.shared_lives {
+            0
+        } else {
+            player
+        }
+    }
+
+    /// Ships start side by side around the centre of the screen
+    fn spawn_position(&self, player: usize, player_count: usize) -> Vec2 {
+        let offset = player as f32 - (player_count as f32 - 1.0) / 2.0;
+        Vec2::new(
+            self.bounds.x / 2.0 + offset * SHIP_SPAWN_SPACING,
+            self.bounds.y / 2.0,
+        )
+    }
+
+    /// Ship the saucers aim at, the closest one still flying
+    fn nearest_ship(&self, pos: Vec2) -> Option<&Ship> {
+        self.players
+            .iter()
+            .map(|p| &p.ship)
+            .filter(|s| !s.state.is_destroyed())
+            .min_by(|a, b| {
+                wrapped_distance(pos, a.pos, self.bounds).total_cmp(&wrapped_distance(
+                    pos,
+                    b.pos,
+                    self.bounds,
+                ))
+            })
+    }
+
+    fn add_score_text(&mut self, text: String, pos: Vec2, color: Color) {
+        self.score_texts.push(ScoreText {
+            text,
+            color,
+            ttl: SCORE_TEXT_TTL,
+            time: 0.0,
+            pos,
+        });
+    }
+
+    /// Brings in a saucer, counting the bullets already in the air as shots at it
+    fn spawn_saucer(&mut self, size: SaucerSize) {
+        let saucer = Saucer {
+            shots: self.players.iter().map(|p| p.bullets.len()).collect(),
+            ..Saucer::new(size, self.bounds)
+        };
+        self.events.push(GameEvent::SaucerSpawned {
+            size,
+            pos: saucer.pos,
+        });
+        self.saucers.push(saucer);
+    }
+
+    /// Scores a kill at the player's current multiplier and extends their combo
+    fn award_kill(&mut self, player: usize, points: usize, pos: Vec2) {
+        let player = &mut self.players[player];
+        let multiplier = player.combo_multiplier();
+        let points = points * multiplier;
+        player.score += points;
+        player.combo += 1;
+        player.combo_time = 0.0;
+
+        let color = if multiplier > 1 { COMBO_COLOR } else { WHITE };
+        self.add_score_text(points.to_string(), pos, color);
+    }
+
+    /// Distance from the position to the edge of the closest threat
+    fn threat_distance(&self, pos: Vec2) -> f32 {
+        let asteroids = self
+            .asteroids
+            .iter()
+            .map(|a| wrapped_distance(pos, a.pos, self.bounds) - a.size.size());
+        let saucers = self
+            .saucers
+            .iter()
+            .map(|s| wrapped_distance(pos, s.pos, self.bounds) - s.size.size());
+        let bullets = self
+            .saucer_bullets
+            .iter()
+            .map(|(b, _)| wrapped_distance(pos, b.pos, self.bounds) - BULLET_SIZE);
+
+        asteroids
+            .chain(saucers)
+            .chain(bullets)
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    fn hyperspace_destination(&self, from: Vec2) -> Vec2 {
+        match self.hyperspace_rules {
+            HyperspaceRules::Modern => loop {
+                let pos = random_position(self.bounds);
+                if from.distance(pos) > SHIP_HYPERSPACE_MIN_DISTANCE {
+                    break pos;
+                }
+            },
+            HyperspaceRules::Arcade => random_position(self.bounds),
+            HyperspaceRules::Safe => {
+                let cell = self.bounds / SAFE_HYPERSPACE_GRID as f32;
+                let cells = SAFE_HYPERSPACE_GRID * SAFE_HYPERSPACE_GRID;
+                // start from a random cell so ties, such as an empty screen, are broken randomly
+                let offset = rand::gen_range(0, cells);
+                (0..cells)
+                    .map(|i| (i + offset) % cells)
+                    .map(|i| {
+                        let (x, y) = (i % SAFE_HYPERSPACE_GRID, i / SAFE_HYPERSPACE_GRID);
+                        // jitter within the cell so repeated jumps don't land on a fixed grid
+                        Vec2::new(
+                            (x as f32 + rand::gen_range(0.25, 0.75)) * cell.x,
+                            (y as f32 + rand::gen_range(0.25, 0.75)) * cell.y,
+                        )
+                    })
+                    .map(|pos| (pos, self.threat_distance(pos)))
+                    .fold((from, f32::NEG_INFINITY), |best, candidate| {
+                        if candidate.1 > best.1 {
+                            candidate
+                        } else {
+                            best
+                        }
+                    })
+                    .0
+            }
+        }
+    }
+
+    fn hyperspace_failure_chance(&self) -> f32 {
+        match self.hyperspace_rules {
+            HyperspaceRules::Arcade => {
+                let objects = self.asteroids.len() + self.saucers.len() + self.saucer_bullets.len();
+                (ARCADE_HYPERSPACE_BASE_FAILURE
+                    + ARCADE_HYPERSPACE_FAILURE_PER_OBJECT * objects as f32)
+                    .min(ARCADE_HYPERSPACE_MAX_FAILURE)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Starts spawning the asteroids of a wave, counting from one
+    fn start_wave(&mut self, wave: usize) {
+        self.wave_time = 0.0;
+        self.wave_deaths = 0;
+        self.asteroid_wave = wave.saturating_sub(1);
+        self.asteroids_spawned_in_wave = 0;
+        self.saucers_spawned_in_wave = 0;
+        self.events.push(GameEvent::WaveStarted {
+            wave: self.asteroid_wave + 1,
+        });
+    }
+
+    fn update(&mut self, delta_t: f32, inputs: &[ShipInput]) {
+        let bounds = self.bounds;
+        let was_over = self.game_over;
+        self.frame += 1;
+        self.profile.start();
+
+        // Asteroid Spawning
+        self.last_asteroid += delta_t;
+        if self.asteroids_spawned_in_wave < self.get_wave_asteroid_amount()
+            && self.last_asteroid >= self.next_asteroid_spawn_rate
+        {
+            self.last_asteroid = 0.0;
+            self.asteroids_spawned_in_wave += 1;
+            self.next_asteroid_spawn_rate =
+                rand::gen_range(ASTEROID_MIN_SPAWN_RATE, self.max_asteroid_spawn_rate);
+            self.asteroids.push(Asteroid::new(
+                AsteroidType::Large,
+                random_edge_position(self.bounds),
+                random_unit_vector() * AsteroidType::Large.speed(),
+            ));
+        }
+
+        self.max_asteroid_spawn_rate -= (self.max_asteroid_spawn_rate - ASTEROID_MIN_SPAWN_RATE)
+            * delta_t
+            * self.tuning.asteroid_spawn_decrease_factor;
+
+        // Saucer Spawning
+
+        self.last_saucer += delta_t;
+        if self.saucers_spawned_in_wave < SAUCER_MAX_PER_WAVE
+            && self.saucers.len() < SAUCER_MAX
+            && self.last_saucer > self.tuning.saucer_spawn_rate
+        {
+            self.last_saucer = 0.0;
+            self.saucers_spawned_in_wave += 1;
+            self.spawn_saucer(SaucerSize::from_score(self.total_score()));
+        }
+        self.profile.lap(Phase::Spawning);
+
+        // Ship Logic
+
+        // what destroyed each ship this frame, if anything
+        let mut ship_hits: Vec<Option<DeathCause>> = (0..self.players.len())
+            .map(|i| {
+                self.update_player(i, inputs.get(i).copied().unwrap_or_default(), delta_t)
+                    .then_some(DeathCause::Hyperspace)
+            })
+            .collect();
+        self.profile.lap(Phase::Ships);
+
+        // Game logic
+
+        self.particles.iter_mut().for_each(|p| {
+            p.pos += p.vel * delta_t;
+            p.size = PARTICLE_SIZE * (1.0 - p.time / p.ttl);
+            p.color.a = 1.0 - p.time / p.ttl;
+            p.time += delta_t;
+        });
+        self.particles.retain(|p| p.time < p.ttl);
+
+        self.debris.iter_mut().for_each(|d| {
+            d.pos += d.vel * delta_t;
+            d.rot += d.spin * delta_t;
+            d.color.a = 1.0 - d.time / d.ttl;
+            d.time += delta_t;
+        });
+        self.debris.retain(|d| d.time < d.ttl);
+
+        self.score_texts.iter_mut().for_each(|t| {
+            t.pos.y -= SCORE_TEXT_SPEED * delta_t;
+            t.color.a = 1.0 - t.time / t.ttl;
+            t.time += delta_t;
+        });
+        self.score_texts.retain(|t| t.time < t.ttl);
+        self.profile.lap(Phase::Particles);
+
+        self.players.iter_mut().for_each(|p| {
+            p.bullets.iter_mut().for_each(|(b, t)| {
+                b.pos += b.vel * delta_t;
+                wrap_position(&mut b.pos, bounds);
+
+                *t += delta_t;
+            });
+        });
+
+        self.asteroids.iter_mut().for_each(|a| {
+            a.pos += a.vel * delta_t;
+            wrap_position(&mut a.pos, bounds);
+        });
+
+        self.saucers.iter_mut().for_each(|s| {
+            s.pos += s.vel * delta_t;
+            wrap_position(&mut s.pos, bounds);
+        });
+
+        let new_saucer_bullets: Vec<(Bullet, f32)> = self
+            .saucers
+            .iter()
+            .filter(|s| s.last_shot + delta_t > SAUCER_BULLET_FREQUENCY)
+            .map(|s| (s.shoot(self.nearest_ship(s.pos)), 0.0))
+            .collect();
+        self.saucer_bullets.extend(new_saucer_bullets);
+        self.saucers.iter_mut().for_each(|s| {
+            s.last_shot += delta_t;
+            if s.last_shot > SAUCER_BULLET_FREQUENCY {
+                s.last_shot = 0.0;
+            }
+        });
+
+        self.saucer_bullets.iter_mut().for_each(|(b, t)| {
+            b.pos += b.vel * delta_t;
+            wrap_position(&mut b.pos, bounds);
+
+            *t += delta_t;
+        });
+
+        self.profile.lap(Phase::Physics);
+
+        self.asteroids.iter_mut().for_each(|a| {
+            self.players.iter_mut().enumerate().for_each(|(i, p)| {
+                p.bullets.iter_mut().for_each(|(b, _)| {
+                    let collided = a.pos.distance(b.pos).abs() < a.size.size() + BULLET_SIZE;
+                    if collided && !a.collided {
+                        a.hit_by = Some(i);
+                    }
+                    a.collided = a.collided || collided;
+                    b.collided = b.collided || collided;
+                });
+            });
+        });
+
+        self.saucers.iter_mut().for_each(|s| {
+            self.players.iter_mut().enumerate().for_each(|(i, p)| {
+                p.bullets.iter_mut().for_each(|(b, _)| {
+                    let collided = s.pos.distance(b.pos).abs() < s.size.size() + BULLET_SIZE;
+                    if collided && !s.collided {
+                        let shots = s.shots.get(i).copied().unwrap_or_default();
+                        s.hit_by = Some((i, Kill::Shot { shots }));
+                    }
+                    s.collided = s.collided || collided;
+                    b.collided = b.collided || collided;
+                });
+            });
+        });
+
+        // Energy shield deflects anything that would otherwise hit the ship
+        self.players.iter_mut().for_each(|p| {
+            self.asteroids.iter_mut().for_each(|a| {
+                p.ship.deflect(&mut a.pos, &mut a.vel, a.size.size());
+            });
+
+            self.saucers.iter_mut().for_each(|s| {
+                p.ship.deflect(&mut s.pos, &mut s.vel, s.size.size());
+            });
+
+            self.saucer_bullets.iter_mut().for_each(|(b, _)| {
+                let mut vel = b.vel;
+                b.collided = b.collided || p.ship.deflect(&mut b.pos, &mut vel, BULLET_SIZE);
+            });
+        });
+
+        for (i, ship_hit) in ship_hits.iter_mut().enumerate() {
+            let ship = &self.players[i].ship;
+
+            self.asteroids.iter_mut().for_each(|a| {
+                let collided = ship.is_vulnerable()
+                    && ship.pos.distance(a.pos) < SHIP_COLLISION_RADIUS + a.size.size();
+                if collided && !a.collided {
+                    a.hit_by = Some(i);
+                }
+                if collided {
+                    ship_hit.get_or_insert(DeathCause::Asteroid(a.size));
+                }
+                a.collided = a.collided || collided;
+            });
+
+            self.saucers.iter_mut().for_each(|s| {
+                let collided = ship.is_vulnerable()
+                    && ship.pos.distance(s.pos) < SHIP_COLLISION_RADIUS + s.size.size();
+                if collided && !s.collided {
+                    s.hit_by = Some((i, Kill::Rammed));
+                }
+                if collided {
+                    ship_hit.get_or_insert(DeathCause::Saucer);
+                }
+                s.collided = s.collided || collided;
+            });
+
+            self.saucer_bullets.iter_mut().for_each(|(b, _)| {
+                let collided = ship.is_vulnerable()
+                    && ship.pos.distance(b.pos) < SHIP_COLLISION_RADIUS + BULLET_SIZE;
+                if collided {
+                    ship_hit.get_or_insert(DeathCause::SaucerBullet);
+                }
+                b.collided = b.collided || collided;
+            });
+        }
+
+        if self.friendly_fire {
+            for (i, ship_hit) in ship_hits.iter_mut().enumerate() {
+                let ship = &self.players[i].ship;
+                if !ship.is_vulnerable() {
+                    continue;
+                }
+                let pos = ship.pos;
+
+                self.players
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .for_each(|(j, p)| {
+                        p.bullets.iter_mut().for_each(|(b, _)| {
+                            let collided =
+                                pos.distance(b.pos) < SHIP_COLLISION_RADIUS + BULLET_SIZE;
+                            if collided {
+                                ship_hit.get_or_insert(DeathCause::PlayerBullet(j));
+                            }
+                            b.collided = b.collided || collided;
+                        });
+                    });
+            }
+        }
+
+        // scored before the ships are lost, so ramming something doesn't leave a dead ship a combo
+        let kills: Vec<(usize, usize, Vec2)> = self
+            .asteroids
+            .iter()
+            .filter(|a| a.collided)
+            .filter_map(|a| a.hit_by.map(|i| (i, a.size.score(), a.pos)))
+            .chain(
+                self.saucers
+                    .iter()
+                    .filter(|s| s.collided)
+                    .filter_map(|s| s.hit_by.map(|(i, _)| (i, s.size.score(), s.pos))),
+            )
+            .collect();
+        kills
+            .into_iter()
+            .for_each(|(player, points, pos)| self.award_kill(player, points, pos));
+
+        let god = self.god;
+        for (i, cause) in ship_hits
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, hit)| Some((i, hit?)))
+            .filter(|_| !god)
+        {
+            let pos = self.players[i].ship.pos;
+            self.destroy_ship(i, cause);
+            if let (DeathCause::PlayerBullet(killer), Some(_)) = (cause, self.deathmatch) {
+                self.players[killer].kills += 1;
+                self.award_kill(killer, DEATHMATCH_KILL_SCORE, pos);
+            }
+        }
+
+        let mut new_asteroid_particles = self
+            .asteroids
+            .iter()
+            .filter(|a| a.collided)
+            .flat_map(|a| {
+                (0..ASTEROID_PARTICLE_SPAWN)
+                    .map(|_| Particle {
+                        color: ASTEROID_PARTICLE_COLOR,
+                        ttl: ASTEROID_PARTICLE_TTL,
+                        time: 0.0,
+                        pos: a.pos,
+                        vel: random_unit_vector() * ASTEROID_PARTICLE_SPEED,
+                        size: PARTICLE_SIZE,
+                    })
+                    .collect::<Vec<Particle>>()
+            })
+            .collect();
+        self.particles.append(&mut new_asteroid_particles);
+
+        let mut new_saucer_particles = self
+            .saucers
+            .iter()
+            .filter(|s| s.collided)
+            .flat_map(|s| {
+                (0..SAUCER_PARTICLE_SPAWN)
+                    .map(|_| Particle {
+                        color: SAUCER_PARTICLE_COLOR,
+                        ttl: SAUCER_PARTICLE_TTL,
+                        time: 0.0,
+                        pos: s.pos,
+                        vel: random_unit_vector() * SAUCER_PARTICLE_SPEED,
+                        size: PARTICLE_SIZE,
+                    })
+                    .collect::<Vec<Particle>>()
+            })
+            .collect();
+        self.particles.append(&mut new_saucer_particles);
+
+        self.players.iter_mut().for_each(|p| {
+            p.combo_time += delta_t;
+            // a bullet running out without hitting anything breaks the combo
+            if p.combo_time > COMBO_WINDOW
+                || p.bullets
+                    .iter()
+                    .any(|(b, t)| !b.collided && *t > BULLET_TTL)
+            {
+                p.combo = 0;
+            }
+        });
+
+        self.events.extend(
+            self.asteroids
+                .iter()
+                .filter(|a| a.collided)
+                .map(|a| GameEvent::AsteroidDestroyed {
+                    size: a.size,
+                    by: a.hit_by,
+                    pos: a.pos,
+                })
+                .chain(self.saucers.iter().filter(|s| s.collided).map(|s| {
+                    GameEvent::SaucerDestroyed {
+                        size: s.size,
+                        by: s.hit_by.map(|(i, _)| i),
+                        kill: s.hit_by.map(|(_, kill)| kill),
+                        pos: s.pos,
+                    }
+                })),
+        );
+
+        let total_score = self.total_score();
+        for i in 0..self.players.len() {
+            if self.lives_owner(i) == i {
+                let score = if self.shared_lives {
+                    total_score
+                } else {
+                    self.players[i].score
+                };
+                let events = self.players[i].lives.update_score(score);
+                self.handle_life_events(i, events);
+            }
+        }
+
+        self.players.iter_mut().for_each(|p| {
+            if let Some((_, t)) = p.heart_animation.as_mut() {
+                *t += delta_t;
+            }
+            p.heart_animation = p.heart_animation.filter(|(_, t)| *t < HEART_ANIMATION_TIME);
+        });
+
+        let mut new_asteroids: Vec<Asteroid> = self
+            .asteroids
+            .iter()
+            .filter(|a| a.collided)
+            .flat_map(|a| a.split())
+            .flatten()
+            .collect();
+
+        self.asteroids.retain(|a| !a.collided);
+        self.asteroids.append(&mut new_asteroids);
+        self.players
+            .iter_mut()
+            .for_each(|p| p.bullets.retain(|(b, t)| !(b.collided || *t > BULLET_TTL)));
+        self.saucers.retain(|s| !s.collided);
+        self.saucer_bullets
+            .retain(|(b, t)| !(b.collided || *t > SAUCER_BULLET_TTL));
+
+        self.profile.lap(Phase::Collisions);
+
+        // bullets leave a trail
+        if self.frame.is_multiple_of(10) {
+            self.players.iter().for_each(|p| {
+                p.bullets.iter().for_each(|(b, _)| {
+                    self.particles.push(Particle {
+                        color: BULLET_COLOR,
+                        ttl: 0.2,
+                        time: 0.0,
+                        vel: Vec2::ZERO,
+                        pos: b.pos,
+                        size: BULLET_SIZE,
+                    });
+                });
+            });
+            self.saucer_bullets.iter().for_each(|(b, _)| {
+                self.particles.push(Particle {
+                    color: SAUCER_BULLET_COLOR,
+                    ttl: 0.2,
+                    time: 0.0,
+                    vel: Vec2::ZERO,
+                    pos: b.pos,
+                    size: BULLET_SIZE,
+                });
+            });
+        }
+
+        self.profile.lap(Phase::Trails);
+
+        self.wave_time += delta_t;
+        if self.asteroids_spawned_in_wave == self.get_wave_asteroid_amount()
+            && self.asteroids.is_empty()
+        {
+            let mut bonus = 0;
+            if self.wave_time < WAVE_QUICK_CLEAR_TIME {
+                bonus += WAVE_QUICK_CLEAR_BONUS;
+            }
+            if self.wave_deaths == 0 {
+                bonus += WAVE_NO_DEATH_BONUS;
+            }
+            if bonus > 0 {
+                self.players
+                    .iter_mut()
+                    .filter(|p| !p.out_of_lives)
+                    .for_each(|p| p.score += bonus);
+                self.add_score_text(
+                    format!("Wave Bonus {}", bonus),
+                    self.bounds / 2.0,
+                    COMBO_COLOR,
+                );
+            }
+
+            self.start_wave(self.asteroid_wave + 2);
+        }
+
+        if let Some(rules) = self.deathmatch {
+            self.match_time += delta_t;
+            if self.match_time >= rules.time_limit
+                || self.players.iter().any(|p| p.kills >= rules.kill_limit)
+            {
+                self.game_over = true;
+            }
+        }
+
+        // Game over once every ship is gone and has finished exploding
+        if self
+            .players
+            .iter()
+            .all(|p| p.out_of_lives && p.ship.respawn_time > SHIP_RESPAWN_DELAY)
+        {
+            self.game_over = true;
+        }
+        if self.game_over && !was_over {
+            self.events.push(GameEvent::GameOver);
+        }
+    }
+
+    /// Flies the player's ship, returning whether it was destroyed by a failed hyperspace jump
+    fn update_player(&mut self, index: usize, input: ShipInput, delta_t: f32) -> bool {
+        let mut hyperspace_failed = false;
+        let bounds = self.bounds;
+
+        let spawn = self.spawn_position(index, self.players.len());
+        let spawn_clear = self.threat_distance(spawn) > SHIP_RESPAWN_CLEAR_RADIUS;
+        // deathmatch ships jump back in from where they were destroyed
+        let respawn_jump = (self.deathmatch.is_some()
+            && self.players[index].ship.state.is_destroyed())
+        .then(|| self.hyperspace_destination(self.players[index].ship.pos));
+        let jump = input.hyperspace.then(|| {
+            (
+                self.hyperspace_destination(self.players[index].ship.pos),
+                rand::gen_range(0.0, 1.0) < self.hyperspace_failure_chance(),
+            )
+        });
+
+        let frame = self.frame;
+        let bullet_lead = self.bullet_lead;
+        let player = &mut self.players[index];
+
+        player.last_bullet += delta_t;
+        player.last_hyperspace += delta_t;
+
+        if let ShipState::Shielded = player.ship.state {
+            player.ship.shield_time += delta_t;
+            if player.ship.shield_time > player.ship.shield_duration {
+                player.ship.state = ShipState::Normal;
+            }
+        }
+
+        player.ship.thrusting = false;
+        if player.ship.state.is_destroyed() {
+            player.ship.respawn_time += delta_t;
+            let respawn_due = player.ship.respawn_time > SHIP_RESPAWN_DELAY && !player.out_of_lives;
+            if let (true, Some(to)) = (respawn_due, respawn_jump) {
+                let from = player.ship.pos;
+                player.ship = Ship {
+                    pos: from,
+                    color: player.color,
+                    state: ShipState::Hyperdrive,
+                    current_translation: Translation {
+                        from,
+                        to,
+                        duration: (from.distance(to) / SHIP_HYPERSPACE_SPEED).max(f32::EPSILON),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+            } else if respawn_due && spawn_clear {
+                player.ship = Ship {
+                    pos: spawn,
+                    color: player.color,
+                    state: ShipState::Shielded,
+                    shield_duration: SHIP_SPAWN_PROTECTION_TIME,
+                    ..Default::default()
+                };
+            }
+        } else if !player.ship.state.is_translating() {
+            let ship = &mut player.ship;
+            if input.left {
+                ship.rot -= SHIP_ROTATION_SPEED * delta_t;
+            }
+            if input.right {
+                ship.rot += SHIP_ROTATION_SPEED * delta_t;
+            }
+
+            ship.thrusting = input.thrust;
+            if input.thrust {
+                ship.acc = ship.get_unit_direction() * SHIP_ACCELERATION;
+                if frame.is_multiple_of(5) {
+                    self.particles.push(Particle {
+                        color: ORANGE,
+                        ttl: 0.2,
+                        time: 0.0,
+                        pos: ship.pos - (ship.get_unit_direction() * SHIP_COLLISION_RADIUS * 0.7),
+                        vel: Vec2::ZERO,
+                        size: SHIP_WIDTH * 0.3,
+                    });
+                }
+            } else {
+                ship.acc = -SHIP_DRAG * ship.vel * ship.vel.length();
+            }
+
+            ship.vel += ship.acc * delta_t;
+
+            if ship.vel.length() > self.tuning.ship_max_speed {
+                ship.vel = ship.vel.normalize() * self.tuning.ship_max_speed;
+            }
+
+            ship.pos += ship.vel * delta_t;
+
+            if input.fire
+                && player.last_bullet > BULLET_FREQUENCY
+                && player.bullets.len() < MAX_BULLETS
+            {
+                player.last_bullet = 0.0;
+                // move the bullet on to where it would be had the input not been delayed
+                let vel = player.ship.get_unit_direction() * BULLET_SPEED;
+                player.bullets.push((
+                    Bullet {
+                        pos: player.ship.pos + vel * bullet_lead,
+                        vel,
+                        collided: false,
+                    },
+                    bullet_lead,
+                ));
+                self.saucers
+                    .iter_mut()
+                    .filter_map(|s| s.shots.get_mut(index))
+                    .for_each(|shots| *shots += 1);
+                self.events.push(GameEvent::BulletFired {
+                    player: index,
+                    pos: player.ship.pos,
+                });
+            }
+
+            if let Some((pos, malfunction)) = jump {
+                if player.last_hyperspace > SHIP_HYPERSPACE_FREQUENCY {
+                    player.last_hyperspace = 0.0;
+                    let dist = player.ship.pos.distance(pos);
+                    player.ship.current_translation = Translation {
+                        from: player.ship.pos,
+                        to: pos,
+                        // guard against a zero length jump
+                        duration: (dist / SHIP_HYPERSPACE_SPEED).max(f32::EPSILON),
+                        ..Default::default()
+                    };
+                    player.ship.hyperspace_malfunction = malfunction;
+                    player.ship.state = ShipState::Hyperdrive;
+                    self.events.push(GameEvent::HyperspaceUsed {
+                        player: index,
+                        pos: player.ship.current_translation.from,
+                    });
+                }
+            }
+        } else {
+            let ship = &mut player.ship;
+            ship.pos = ship.current_translation.get();
+            ship.color.a = 0.5 + (ship.current_translation.current_time * 20.0).cos() * 0.5;
+
+            ship.current_translation.current_time += delta_t;
+
+            if ship.current_translation.current_time > ship.current_translation.duration {
+                ship.state = ShipState::Normal;
+                ship.color = player.color;
+                hyperspace_failed = std::mem::take(&mut ship.hyperspace_malfunction);
+            }
+        }
+
+        let ship = &mut player.ship;
+        wrap_position(&mut ship.pos, bounds);
+
+        // Energy Shield
+
+        let shield_held =
+            input.shield && !ship.state.is_translating() && !ship.state.is_destroyed();
+        ship.energy_shield_active = shield_held
+            && ship.energy_shield_energy > 0.0
+            && (ship.energy_shield_active
+                || ship.energy_shield_energy >= ENERGY_SHIELD_MIN_ACTIVATION_ENERGY);
+
+        if ship.energy_shield_active {
+            ship.energy_shield_idle_time = 0.0;
+            ship.energy_shield_energy =
+                (ship.energy_shield_energy - ENERGY_SHIELD_DRAIN * delta_t).max(0.0);
+        } else {
+            ship.energy_shield_idle_time += delta_t;
+            if ship.energy_shield_idle_time > ENERGY_SHIELD_RECHARGE_DELAY {
+                ship.energy_shield_energy = (ship.energy_shield_energy
+                    + ENERGY_SHIELD_RECHARGE * delta_t)
+                    .min(ENERGY_SHIELD_MAX_ENERGY);
+            }
+        }
+
+        hyperspace_failed
+    }
+
+    fn destroy_ship(&mut self, index: usize, cause: DeathCause) {
+        let owner = self.lives_owner(index);
+        let (events, out_of_lives) = if self.deathmatch.is_some() {
+            (Vec::new(), false)
+        } else {
+            let events = self.players[owner].lives.lose();
+            (events, self.players[owner].lives.is_game_over())
+        };
+
+        // everyone drawing on the lives is out once they run out, not just the ship that lost
+        // the last one
+        if out_of_lives {
+            for i in 0..self.players.len() {
+                if self.lives_owner(i) == owner {
+                    self.players[i].out_of_lives = true;
+                }
+            }
+        }
+        let player = &mut self.players[index];
+        player.combo = 0;
+        // in casual mode the ship only breaks up once there are no lives left
+        match (self.death_mode, out_of_lives) {
+            (DeathMode::Respawn, _) | (DeathMode::Casual, true) => {
+                self.debris.append(&mut player.ship.break_apart());
+                player.ship.state = ShipState::Destroyed;
+                player.ship.respawn_time = 0.0;
+                player.ship.energy_shield_active = false;
+            }
+            (DeathMode::Casual, false) => {
+                player.ship.state = ShipState::Shielded;
+                player.ship.shield_time = 0.0;
+                player.ship.shield_duration = SHIP_SHIELD_TIME;
+            }
+        }
+        self.wave_deaths += 1;
+        self.events.push(GameEvent::ShipHit {
+            player: index,
+            cause,
+            pos: self.players[index].ship.pos,
+        });
+
+        self.handle_life_events(owner, events);
+    }
+
+    fn handle_life_events(&mut self, player: usize, events: Vec<LifeEvent>) {
+        for event in events {
+            match event {
+                LifeEvent::LifeLost => self.players[player].heart_animation = Some((event, 0.0)),
+                LifeEvent::ExtraLife => {
+                    self.players[player].heart_animation = Some((event, 0.0));
+                    self.events.push(GameEvent::ExtraLife { player });
+                }
+                // players are taken out of play as their ships are destroyed
+                LifeEvent::GameOver => (),
+            }
+        }
+    }
+
+    fn draw(&self, view: &View) {
+        let camera = view.camera.apply(playfield_camera(self.bounds));
+        match &view.glow {
+            Some(glow) => set_camera(&glow.camera(camera)),
+            None => set_camera(&camera),
+        }
+        clear_background(BACKGROUND_COLOR);
+        if let Some(material) = &view.material {
+            gl_use_material(material);
+        }
+
+        Screen.render(&self.frame(view));
+
+        gl_use_default_material();
+        set_default_camera();
+        if let Some(glow) = &view.glow {
+            glow.present();
+        }
+    }
+
+    /// Everything on the playfield this frame, in playfield coordinates
+    fn frame(&self, view: &View) -> Frame {
+        let mut frame = Frame::default();
+        view.star_map.iter().for_each(|(p, r)| {
+            frame.circle(
+                *p * self.bounds,
+                *r,
+                STAR_COLORS[rand::gen_range(0, STAR_COLORS.len())],
+            );
+        });
+
+        // Game
+        self.particles
+            .iter()
+            .for_each(|p| frame.circle(p.pos, p.size, p.color));
+
+        self.debris.iter().for_each(|d| {
+            let end = Vec2::from_angle(d.rot).rotate(d.half_length);
+            frame.line(d.pos - end, d.pos + end, 2.0, d.color);
+        });
+
+        self.players
+            .iter()
+            .for_each(|p| draw_ship(&mut frame, &p.ship));
+
+        self.asteroids
+            .iter()
+            .for_each(|a| draw_asteroid(&mut frame, a));
+
+        self.saucers.iter().for_each(|s| draw_saucer(&mut frame, s));
+
+        self.players.iter().for_each(|p| {
+            p.bullets
+                .iter()
+                .for_each(|(b, _)| frame.circle(b.pos, BULLET_SIZE, BULLET_COLOR))
+        });
+
+        self.score_texts
+            .iter()
+            .for_each(|t| frame.centered_text(&t.text, t.pos, SCORE_TEXT_SIZE, t.color));
+
+        self.saucer_bullets
+            .iter()
+            .for_each(|(b, _)| frame.circle(b.pos, BULLET_SIZE, SAUCER_BULLET_COLOR));
+
+        // UI
+        let single_player = self.players.len() == 1;
+        for (i, player) in self.players.iter().enumerate() {
+            // one player is centred, two players split the top of the screen
+            let x = if single_player {
+                self.bounds.x / 2.0
+            } else {
+                self.bounds.x * (1.0 + 2.0 * i as f32) / 4.0
+            };
+
+            let (score_text, score_color) = if single_player {
+                (format!("Score: {}", player.score), WHITE)
+            } else {
+                (format!("P{}: {}", i + 1, player.score), player.color)
+            };
+            frame.centered_text(&score_text, Vec2::new(x, 24.0), 32.0, score_color);
+
+            if player.combo_multiplier() > 1 {
+                frame.centered_text(
+                    &format!("Combo x{}", player.combo_multiplier()),
+                    Vec2::new(x, 90.0),
+                    24.0,
+                    COMBO_COLOR,
+                );
+            }
+
+            if self.deathmatch.is_some() {
+                frame.centered_text(
+                    &format!("Kills: {}", player.kills),
+                    Vec2::new(x, 60.0),
+                    24.0,
+                    player.color,
+                );
+            } else if self.lives_owner(i) == i {
+                let x = if self.shared_lives {
+                    self.bounds.x / 2.0
+                } else {
+                    x
+                };
+                draw_hearts(&mut frame, player, x);
+            }
+
+            // first player's bars are on the left when there are two
+            if single_player || i == 1 {
+                draw_ability_bars(
+                    &mut frame,
+                    player,
+                    self.ship_ability,
+                    self.bounds.x - 40.0,
+                    self.bounds.y,
+                    -80.0,
+                );
+            } else {
+                draw_ability_bars(
+                    &mut frame,
+                    player,
+                    self.ship_ability,
+                    40.0,
+                    self.bounds.y,
+                    80.0,
+                );
+            }
+        }
+
+        if let Some(rules) = self.deathmatch {
+            let remaining = (rules.time_limit - self.match_time).max(0.0).ceil() as usize;
+            frame.centered_text(
+                &format!("{}:{:02}", remaining / 60, remaining % 60),
+                Vec2::new(self.bounds.x / 2.0, 24.0),
+                32.0,
+                WHITE,
+            );
+        }
+
+        // Debug last to draw on top
+        if view.debug_overlay {
+            for ship in self.players.iter().map(|p| &p.ship) {
+                // Ship Ppsition
+                frame.circle(ship.pos, 1.0, RED);
+                // Ship collision
+                frame.circle_lines(ship.pos, SHIP_COLLISION_RADIUS, 1.0, RED);
+                // Ship velocity
+                frame.line(ship.pos, ship.pos + ship.vel, 2.0, BLUE);
+                // Ship acceleration
+                frame.line(
+                    ship.pos,
+                    ship.pos + ship.acc * SHIP_MAX_SPEED / SHIP_ACCELERATION,
+                    2.0,
+                    RED,
+                );
+            }
+            // Asteroid spawning info
+            frame.text(
+                &format!(
+                    "Wave: {}({}). Spawned {} Asteroids, {} Saucers. Next Asteroid spawn: {:.2} (max {:.2})",
+                    self.asteroid_wave, self.asteroids_spawned_in_wave, self.asteroids_spawned_in_wave, self.saucers_spawned_in_wave, self.next_asteroid_spawn_rate, self.max_asteroid_spawn_rate
+                ),
+                Vec2::new(5.0, self.bounds.y - 10.0),
+                16.0,
+                RED,
+            );
+            // FPS
+            frame.text(
+                &format!("FPS: {}", get_fps()),
+                Vec2::new(5.0, 20.0),
+                16.0,
+                RED,
+            );
+            // center lines
+            frame.line(
+                Vec2::new(0.0, self.bounds.y / 2.0),
+                Vec2::new(self.bounds.x, self.bounds.y / 2.0),
+                1.0,
+                RED,
+            );
+            frame.line(
+                Vec2::new(self.bounds.x / 2.0, 0.0),
+                Vec2::new(self.bounds.x / 2.0, self.bounds.y),
+                1.0,
+                RED,
+            );
+        }
+        frame
+    }
+}
+
+fn draw_ship(frame: &mut Frame, ship: &Ship) {
+    if !ship.state.is_destroyed() {
+        let (v1, v2, v3) = ship.get_tri();
+        frame.polygon(vec![v1, v2, v3], ship.color);
+    }
+    match ship.state {
+        ShipState::Hyperdrive => frame.circle_lines(
+            ship.current_translation.to,
+            SHIP_COLLISION_RADIUS * 0.5,
+            2.0,
+            RED,
+        ),
+        ShipState::Shielded => {
+            let mut shield_color = SHIP_SHIELD_COLOR;
+            shield_color.a = 0.5 + (ship.shield_time * 20.0).cos() * 0.5;
+            frame.circle_lines(ship.pos, SHIP_COLLISION_RADIUS * 1.5, 3.0, shield_color)
+        }
+        _ => (),
+    };
+    if ship.energy_shield_active {
+        let mut shield_color = ENERGY_SHIELD_COLOR;
+        shield_color.a = 0.4 + 0.6 * ship.energy_shield_energy / ENERGY_SHIELD_MAX_ENERGY;
+        frame.circle_lines(ship.pos, ENERGY_SHIELD_RADIUS, 2.0, shield_color);
+    }
+}
+
+fn draw_hearts(frame: &mut Frame, player: &Player, x: f32) {
+    // a lost heart stays on screen while it fades out
+    let hearts = match player.heart_animation {
+        Some((LifeEvent::LifeLost, _)) => player.lives.remaining() + 1,
+        _ => player.lives.remaining(),
+    };
+    for i in 0..hearts {
+        let offset = if hearts == 1 {
+            0.0
+        } else {
+            ((2.0 * (i as f32)) / ((hearts as f32) - 1.0)) - 1.0
+        };
+        let (scale, mut color) = match player.heart_animation {
+            Some((event, t)) if i == hearts - 1 => {
+                let progress = t / HEART_ANIMATION_TIME;
+                match event {
+                    // pop in, overshooting slightly
+                    LifeEvent::ExtraLife => (progress + 0.5 * (progress * PI).sin(), RED),
+                    // swell and fade away
+                    _ => (
+                        1.0 + progress * 0.5,
+                        Color {
+                            a: 1.0 - progress,
+                            ..RED
+                        },
+                    ),
+                }
+            }
+            _ => (1.0, RED),
+        };
+        // flash the remaining hearts when one is lost
+        if let Some((LifeEvent::LifeLost, t)) = player.heart_animation {
+            if i < hearts - 1 && (t * 20.0).cos() > 0.0 {
+                color = WHITE;
+            }
+        }
+        draw_heart(
+            frame,
+            Vec2::new(x + offset * HEART_RADIUS * (hearts as f32), 60.0),
+            scale,
+            color,
+        );
+    }
+}
+
+/// Draws the player's ability bars starting at x, each following bar offset by spacing
+fn draw_ability_bars(
+    frame: &mut Frame,
+    player: &Player,
+    ability: ShipAbility,
+    x: f32,
+    bottom: f32,
+    spacing: f32,
+) {
+    let mut bar_x = x;
+    if ability.has_hyperspace() {
+        let (fill, hyperspace_bar_colour) = if player.last_hyperspace > SHIP_HYPERSPACE_FREQUENCY {
+            (1.0, GREEN)
+        } else {
+            (player.last_hyperspace / SHIP_HYPERSPACE_FREQUENCY, YELLOW)
+        };
+        draw_ability_bar(
+            frame,
+            bar_x,
+            bottom,
+            fill,
+            hyperspace_bar_colour,
+            "Hyperspace",
+        );
+        bar_x += spacing;
+    }
+    if ability.has_shield() {
+        let ship = &player.ship;
+        let shield_bar_colour = if ship.energy_shield_active {
+            ENERGY_SHIELD_COLOR
+        } else if ship.energy_shield_energy < ENERGY_SHIELD_MIN_ACTIVATION_ENERGY {
+            RED
+        } else {
+            YELLOW
+        };
+        draw_ability_bar(
+            frame,
+            bar_x,
+            bottom,
+            ship.energy_shield_energy / ENERGY_SHIELD_MAX_ENERGY,
+            shield_bar_colour,
+            "Shield",
+        );
+    }
+}
+
+/// Players taking turns arcade style, each with their own game which is
+/// set aside while the other player flies
+struct AlternatingGames {
+    games: Vec<Game>,
+    current: usize,
+    // time left announcing whose turn it is
+    interstitial: f32,
+    // taken from the game as it's updated, as the turn may have passed on by the time they're
+    // wanted
+    events: Vec<GameEvent>,
+}
+
+impl AlternatingGames {
+    fn new(player_count: usize, tuning: Tuning) -> Self {
+        let games = (0..player_count.min(MAX_PLAYERS))
+            .map(|i| {
+                let mut game = Game::new(1, screen_bounds());
+                game.tuning = tuning;
+                game.players[0].color = PLAYER_COLORS[i];
+                game.players[0].ship.color = PLAYER_COLORS[i];
+                game
+            })
+            .collect();
+        Self {
+            games,
+            current: 0,
+            interstitial: ALTERNATING_INTERSTITIAL_TIME,
+            events: Vec::new(),
+        }
+    }
+
+    /// The ship has been destroyed and its explosion has finished
+    fn turn_finished(game: &Game) -> bool {
+        let ship = &game.players[0].ship;
+        game.game_over || (ship.state.is_destroyed() && ship.respawn_time > SHIP_RESPAWN_DELAY)
+    }
+
+    fn update(&mut self, delta_t: f32, controls: &[Controls; MAX_PLAYERS]) {
+        if self.interstitial > 0.0 {
+            self.interstitial -= delta_t;
+            return;
+        }
+
+        let game = &mut self.games[self.current];
+        game.bounds = screen_bounds();
+        let was_finished = Self::turn_finished(game);
+        game.update(delta_t, &[controls[self.current].read(game.ship_ability)]);
+        self.events.extend(game.take_events());
+
+        // hand over to the next player still in the game once the turn ends
+        if !was_finished && Self::turn_finished(game) {
+            let count = self.games.len();
+            if let Some(next) = (1..=count)
+                .map(|offset| (self.current + offset) % count)
+                .find(|&i| !self.games[i].game_over)
+            {
+                if next != self.current {
+                    self.current = next;
+                    self.interstitial = ALTERNATING_INTERSTITIAL_TIME;
+                }
+            }
+        }
+    }
+
+    fn draw(&self, view: &View) {
+        self.games[self.current].draw(view);
+        Screen.render(&self.interstitial_frame(screen_bounds()));
+    }
+
+    /// Whose turn it is, over the playfield while the turn is being announced
+    fn interstitial_frame(&self, screen: Vec2) -> Frame {
+        let mut frame = Frame::default();
+        if self.interstitial > 0.0 {
+            frame.centered_text(
+                &format!("PLAYER {}", self.current + 1),
+                Vec2::new(screen.x / 2.0, screen.y / 2.0 - 60.0),
+                64.0,
+                self.games[self.current].players[0].color,
+            );
+        }
+        frame
+    }
+}
+
+/// How the players on the title screen chose to play
+#[derive(Clone, Copy)]
+enum PlayMode {
+    Simultaneous(usize),
+    Alternating(usize),
+    Deathmatch(usize),
+    Online(OnlineMode),
+}
+
+/// Screens reached from the title screen other than play
+#[derive(Clone, Copy)]
+enum Menu {
+    Achievements,
+    // the player and control waiting to be bound to a key, if any
+    Settings { rebinding: Option<(usize, usize)> },
+}
+
+// there is only ever one session, so its size doesn't matter
+#[allow(clippy::large_enum_variant)]
+enum Session {
+    Simultaneous(Game),
+    Alternating(AlternatingGames),
+    Online(NetSession),
+}
+
+impl Session {
+    /// Local games are played with the settings, online ones keep the defaults so peers agree
+    fn new(mode: PlayMode, settings: &Settings) -> Self {
+        let tuning = settings.difficulty.tuning();
+        match mode {
+            PlayMode::Simultaneous(players) => Self::Simultaneous(Game {
+                tuning,
+                shared_lives: settings.shared_lives,
+                friendly_fire: settings.friendly_fire,
+                ..Game::new(players, screen_bounds())
+            }),
+            PlayMode::Alternating(players) => {
+                Self::Alternating(AlternatingGames::new(players, tuning))
+            }
+            PlayMode::Deathmatch(players) => Self::Simultaneous(Game {
+                tuning,
+                ..Game::new_deathmatch(players, screen_bounds())
+            }),
+            PlayMode::Online(mode) => {
+                let transport = RelayTransport::connect(&relay_url());
+                Self::Online(NetSession::new(mode, Box::new(transport)))
+            }
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        match self {
+            Self::Simultaneous(game) => game.game_over,
+            Self::Alternating(games) => games.games.iter().all(|g| g.game_over),
+            Self::Online(session) => session.is_over(),
+        }
+    }
+
+    /// Final scores, empty if an online game never got going
+    fn scores(&self) -> Vec<usize> {
+        match self {
+            Self::Simultaneous(game) => game.players.iter().map(|p| p.score).collect(),
+            Self::Alternating(games) => games.games.iter().map(|g| g.players[0].score).collect(),
+            Self::Online(session) => session
+                .game()
+                .map(|game| game.players.iter().map(|p| p.score).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The game being played, none while an online one is still waiting to start
+    fn game(&self) -> Option<&Game> {
+        match self {
+            Self::Simultaneous(game) => Some(game),
+            Self::Alternating(games) => Some(&games.games[games.current]),
+            Self::Online(session) => session.game(),
+        }
+    }
+
+    /// The game being played when it is only on this machine, so it can be changed freely
+    fn local_game_mut(&mut self) -> Option<&mut Game> {
+        match self {
+            Self::Simultaneous(game) => Some(game),
+            Self::Alternating(games) => Some(&mut games.games[games.current]),
+            Self::Online(_) => None,
+        }
+    }
+
+    /// The finished game when it was a deathmatch, for the results screen
+    fn deathmatch(&self) -> Option<&Game> {
+        self.game().filter(|game| game.deathmatch.is_some())
+    }
+
+    fn update(&mut self, delta_t: f32, controls: &[Controls; MAX_PLAYERS]) {
+        match self {
+            Self::Simultaneous(game) => {
+                let inputs: Vec<ShipInput> = controls
+                    .iter()
+                    .take(game.players.len())
+                    .map(|c| c.read(game.ship_ability))
+                    .collect();
+                game.bounds = screen_bounds();
+                game.update(delta_t, &inputs);
+            }
+            Self::Alternating(games) => games.update(delta_t, controls),
+            // online players all use the first player's controls on their own machine
+            Self::Online(session) => {
+                let ability = session
+                    .game()
+                    .map_or(SHIP_ABILITY, |game| game.ship_ability);
+                session.update(delta_t, controls[0].read(ability));
+            }
+        }
+    }
+
+    /// What has happened in play since the last call
+    fn take_events(&mut self) -> Vec<GameEvent> {
+        match self {
+            Self::Simultaneous(game) => game.take_events(),
+            Self::Alternating(games) => std::mem::take(&mut games.events),
+            Self::Online(session) => session.take_events(),
+        }
+    }
+
+    fn draw(&self, view: &View) {
+        match self {
+            Self::Simultaneous(game) => game.draw(view),
+            Self::Alternating(games) => games.draw(view),
+            Self::Online(session) => session.draw(view),
+        }
+    }
+}
+
+/// How play is drawn, following the settings
+struct View {
+    // stars as a fraction of the playfield and their radius
+    star_map: Vec<(Vec2, f32)>,
+    // draws everything in the palette's colour, none for the classic colours
+    material: Option<Material>,
+    // draws through the vector monitor glow, none for the flat renderer
+    glow: Option<Glow>,
+    // shake, hit-stop and zoom from what is happening in play
+    camera: CameraEffects,
+    debug_overlay: bool,
+}
+
+impl View {
+    fn new(settings: &Settings) -> Self {
+        let stars = (STAR_NUM as f32 * settings.star_density).round() as usize;
+        Self {
+            star_map: (0..stars)
+                .map(|_| {
+                    (
+                        Vec2::new(rand::gen_range(0.0, 1.0), rand::gen_range(0.0, 1.0)),
+                        rand::gen_range(0.5, STAR_MAX_SIZE),
+                    )
+                })
+                .collect(),
+            material: palette::material(settings.palette),
+            glow: settings.glow.then(Glow::new).flatten(),
+            camera: CameraEffects::new(settings),
+            debug_overlay: settings.debug_overlay,
+        }
+    }
+}
+
+/// Local games fill the window, online ones use the fixed NET_PLAYFIELD
+fn screen_bounds() -> Vec2 {
+    Vec2::new(screen_width(), screen_height())
+}
+
+/// Results for a finished deathmatch, the winner followed by everyone's kills
+fn match_results(frame: &mut Frame, game: &Game, screen: Vec2) {
+    let (title, color) = match game.match_winner() {
+        Some(i) => (format!("PLAYER {} WINS", i + 1), game.players[i].color),
+        None => ("DRAW".to_string(), WHITE),
+    };
+    frame.centered_text(
+        &title,
+        Vec2::new(screen.x / 2.0, screen.y / 2.0 - 60.0),
+        64.0,
+        color,
+    );
+
+    let results = game
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, p)| format!("P{}: {} kills, {} points", i + 1, p.kills, p.score))
+        .collect::<Vec<String>>()
+        .join("   ");
+    frame.centered_text(&results, screen / 2.0, 32.0, BLACK);
+}
+
+/// The end of a session: a deathmatch's results, or everyone's final score
+fn game_over_frame(session: &Session, screen: Vec2) -> Frame {
+    let mut frame = Frame::default();
+    frame.rectangle(0.0, 0.0, screen.x, screen.y, MAROON);
+
+    if let Some(game) = session.deathmatch() {
+        match_results(&mut frame, game, screen);
+        return frame;
+    }
+    let scores = session.scores();
+    let final_score = if scores.is_empty() {
+        "Disconnected".to_string()
+    } else {
+        scores
+            .iter()
+            .enumerate()
+            .map(|(i, score)| {
+                if scores.len() == 1 {
+                    format!("Final Score: {}", score)
+                } else {
+                    format!("P{}: {}", i + 1, score)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("   ")
+    };
+    frame.centered_text(&final_score, screen / 2.0, 48.0, BLACK);
+    frame
+}
+
+/// The title and everyone's keys, over the attract mode demo when it's playing
+fn title_frame(settings: &Settings, screen: Vec2, background: bool) -> Frame {
+    let mut frame = Frame::default();
+    if background {
+        frame.rectangle(0.0, 0.0, screen.x, screen.y, BACKGROUND_COLOR);
+    }
+    frame.centered_text(
+        "Asteroids",
+        Vec2::new(screen.x / 2.0, screen.y / 2.0 - 60.0),
+        64.0,
+        WHITE,
+    );
+    let keys = settings
+        .controls
+        .iter()
+        .enumerate()
+        .map(|(i, controls)| {
+            let keys = controls.keys().map(|key| format!("{key:?}"));
+            format!("P{}: {}", i + 1, keys.join(", "))
+        })
+        .collect::<Vec<String>>()
+        .join("    ");
+    frame.centered_text(
+        &keys,
+        Vec2::new(screen.x / 2.0, screen.y - 30.0),
+        16.0,
+        GRAY,
+    );
+    frame
+}
+
+/// Where to find the relay, native builds can point elsewhere with ASTEROIDS_RELAY
+fn relay_url() -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(url) = std::env::var("ASTEROIDS_RELAY") {
+        return url;
+    }
+    RELAY_URL.to_string()
+}
+
+/// JSON lines log of everything that happens in play, native builds write one to the file named
+/// by ASTEROIDS_EVENT_LOG
+fn event_log() -> Option<EventLog> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(path) = std::env::var("ASTEROIDS_EVENT_LOG") {
+        match std::fs::File::create(&path) {
+            Ok(file) => return Some(EventLog::new(std::io::BufWriter::new(file))),
+            Err(e) => eprintln!("can't write the event log to {path}: {e}"),
+        }
+    }
+    None
+}
+
+/// Runs the game in the window macroquad has opened
+pub async fn run() {
+    // the game being played and how it was chosen, none while on the title screen
+    let mut session: Option<(PlayMode, Session)> = None;
+
+    // use the small variance in start up time to seed the random number generator
+    let time = (get_time() * 100_000_000_000.0) as u64;
+    rand::srand(time);
+
+    // game flown by the pilot behind the title screen once nobody has touched anything for a while
+    let mut demo = Game::new(1, screen_bounds());
+    let mut idle_time = 0.0;
+
+    let mut settings = Settings::load();
+    let mut view = View::new(&settings);
+    let mut audio = Audio::load(settings).await;
+    let mut achievements = Achievements::load();
+    // screen opened from the title screen, if any
+    let mut menu: Option<Menu> = None;
+    let mut event_log = event_log();
+    let mut console = Console::default();
+    let mut time = TimeControl::default();
+    let mut profiler = Profiler::default();
+    // time since the session started, for the event log
+    let mut session_time = 0.0;
+
+    loop {
+        if DEBUG && is_key_down(KeyCode::Escape) {
+            break;
+        }
+        if is_key_pressed(KeyCode::F3) {
+            settings.debug_overlay = !settings.debug_overlay;
+            view.debug_overlay = settings.debug_overlay;
+            settings.save();
+        }
+
+        if let Some(screen) = &mut menu {
+            audio.update(None, 0.0);
+            let finished = match screen {
+                Menu::Achievements => achievements.draw_screen(),
+                Menu::Settings { rebinding } => {
+                    let before = settings;
+                    let finished = settings.draw_screen(rebinding);
+                    if settings != before {
+                        audio.set_volumes(settings);
+                    }
+                    if finished {
+                        settings.save();
+                        view = View::new(&settings);
+                    }
+                    finished
+                }
+            };
+            if finished {
+                menu = None;
+            }
+            next_frame().await;
+            continue;
+        }
+
+        let Some((mode, current)) = session.as_mut() else {
+            audio.update(None, 0.0);
+            idle_time += get_frame_time();
+            if !get_keys_pressed().is_empty()
+                || mouse_delta_position() != Vec2::ZERO
+                || is_mouse_button_down(MouseButton::Left)
+            {
+                idle_time = 0.0;
+            }
+
+            let demo_shown = idle_time > ATTRACT_IDLE_TIME;
+            if demo_shown {
+                if demo.game_over {
+                    demo = Game::new(1, screen_bounds());
+                }
+                demo.bounds = screen_bounds();
+                let input = pilot(&demo, 0);
+                demo.update(get_frame_time(), &[input]);
+                demo.take_events();
+                demo.draw(&view);
+            }
+            Screen.render(&title_frame(&settings, screen_bounds(), !demo_shown));
+
+            let modes = [
+                ("1 Player", PlayMode::Simultaneous(1)),
+                ("2 Players", PlayMode::Simultaneous(2)),
+                ("2 Players Alternating", PlayMode::Alternating(2)),
+                ("2 Player Deathmatch", PlayMode::Deathmatch(2)),
+                ("Online Co-op", PlayMode::Online(OnlineMode::Coop)),
+                (
+                    "Online Deathmatch",
+                    PlayMode::Online(OnlineMode::Deathmatch),
+                ),
+            ];
+            for (i, (label, mode)) in modes.into_iter().enumerate() {
+                if root_ui().button(
+                    Vec2::new(
+                        screen_width() / 2.0 - 30.0,
+                        screen_height() / 2.0 + 30.0 * i as f32,
+                    ),
+                    label,
+                ) {
+                    session = Some((mode, Session::new(mode, &settings)));
+                    session_time = 0.0;
+                }
+            }
+            let menus = [
+                ("Achievements", Menu::Achievements),
+                ("Settings", Menu::Settings { rebinding: None }),
+            ];
+            for (i, (label, screen)) in menus.into_iter().enumerate() {
+                if root_ui().button(
+                    Vec2::new(
+                        screen_width() / 2.0 - 30.0,
+                        screen_height() / 2.0 + 30.0 * (modes.len() + i) as f32,
+                    ),
+                    label,
+                ) {
+                    menu = Some(screen);
+                }
+            }
+
+            next_frame().await;
+            continue;
+        };
+
+        if current.is_over() {
+            audio.update(None, 0.0);
+            Screen.render(&game_over_frame(current, screen_bounds()));
+
+            if root_ui().button(
+                Vec2::new(screen_width() / 2.0, screen_height() / 2.0 + 24.0),
+                "Restart?",
+            ) {
+                *current = Session::new(*mode, &settings);
+                session_time = 0.0;
+            } else if root_ui().button(
+                Vec2::new(screen_width() / 2.0, screen_height() / 2.0 + 54.0),
+                "Menu",
+            ) {
+                session = None;
+            }
+
+            next_frame().await;
+            continue;
+        }
+
+        // play stops while the console is open or for a moment on a big hit, though online
+        // games can't be held up without the other players going out of step
+        console.update(current.local_game_mut(), &mut time);
+        view.camera.update(get_frame_time());
+        let local = current.local_game_mut().is_some();
+        let delta_t = if console.holds_play(local) || view.camera.hit_stopped() {
+            None
+        } else {
+            time.update(current.local_game_mut(), get_frame_time())
+        };
+        if let Some(delta_t) = delta_t {
+            current.update(delta_t, &settings.controls);
+            session_time += get_frame_time();
+            let events = current.take_events();
+            view.camera.handle(&events, local);
+            audio.handle(
+                &events,
+                current.game().map_or(screen_bounds(), |g| g.bounds),
+            );
+            audio.update(current.game(), get_frame_time());
+            // nothing earned in a game changed from the console or rewound counts
+            let cheated = current.game().is_some_and(|game| game.cheated);
+            if !cheated && achievements.handle(&events) {
+                achievements.save();
+            }
+            if let Some(log) = &mut event_log {
+                if let Err(e) = log.write(session_time, &events) {
+                    eprintln!("can't write the event log: {e}");
+                    event_log = None;
+                }
+            }
+        } else {
+            audio.update(None, 0.0);
+        }
+
+        let drawing = date::now();
+        current.draw(&view);
+        let update = current.game().filter(|_| delta_t.is_some());
+        profiler.record(
+            get_frame_time(),
+            update.map(|game| &game.profile),
+            date::now() - drawing,
+        );
+        if view.debug_overlay {
+            Screen.render(&profiler.frame(current.game(), screen_bounds()));
+        }
+        Screen.render(&time.frame(screen_bounds()));
+        Screen.render(&console.frame(screen_bounds(), get_time()));
+        achievements.update_toasts(get_frame_time());
+        Screen.render(&achievements.toast_frame(screen_bounds()));
+
+        next_frame().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollback::tests::SIMULATION;
+
+    #[test]
+    fn shared_lives_run_out_for_everyone() {
+        let _lock = SIMULATION.lock().unwrap();
+        rand::srand(1);
+        let mut game = Game {
+            shared_lives: true,
+            ..Game::new(2, Vec2::new(800.0, 600.0))
+        };
+        // the second ship is already waiting to respawn when the first loses the last life
+        game.destroy_ship(1, DeathCause::Saucer);
+        while !game.players[0].lives.is_game_over() {
+            game.destroy_ship(0, DeathCause::Saucer);
+        }
+        assert!(game.players.iter().all(|p| p.out_of_lives));
+
+        for _ in 0..((SHIP_RESPAWN_DELAY + 0.5) * 60.0) as usize {
+            game.update(1.0 / 60.0, &[]);
+        }
+        assert!(game.players[1].ship.state.is_destroyed());
+        assert!(game.game_over);
+    }
+
+    #[test]
+    fn friendly_fire_is_up_to_the_players() {
+        let _lock = SIMULATION.lock().unwrap();
+        for friendly_fire in [false, true] {
+            rand::srand(1);
+            let mut game = Game {
+                friendly_fire,
+                ..Game::new(2, Vec2::new(800.0, 600.0))
+            };
+            game.asteroids.clear();
+            game.asteroids_spawned_in_wave = game.get_wave_asteroid_amount();
+            game.players[1].ship.state = ShipState::Normal;
+            let bullet = Bullet {
+                pos: game.players[1].ship.pos,
+                vel: Vec2::ZERO,
+                collided: false,
+            };
+            game.players[0].bullets.push((bullet, 0.0));
+            game.update(1.0 / 60.0, &[]);
+
+            assert_eq!(game.players[1].ship.state.is_destroyed(), friendly_fire);
+            // co-op partners don't score off each other
+            assert_eq!(game.players[0].kills, 0);
+        }
+    }
+
+    #[test]
+    fn saucer_kills_count_the_shots_their_player_had_in_the_air() {
+        let _lock = SIMULATION.lock().unwrap();
+        rand::srand(1);
+        let mut game = Game::new(2, Vec2::new(800.0, 600.0));
+        game.asteroids.clear();
+        game.asteroids_spawned_in_wave = game.get_wave_asteroid_amount();
+        game.players.iter_mut().for_each(|p| {
+            p.ship.state = ShipState::Normal;
+            p.last_bullet = BULLET_FREQUENCY * 2.0;
+        });
+        // fired before the saucer turned up, but still on its way
+        let stray = Bullet {
+            pos: Vec2::ZERO,
+            vel: Vec2::ZERO,
+            collided: false,
+        };
+        game.players[0].bullets.push((stray, 0.0));
+        game.spawn_saucer(SaucerSize::Small);
+        let ship = &game.players[0].ship;
+        let ahead = ship.pos + ship.get_unit_direction() * 60.0;
+        let saucer = game.saucers.last_mut().unwrap();
+        saucer.pos = ahead;
+        saucer.vel = Vec2::ZERO;
+
+        // the other player shoots the other way, which is no concern of the first player's
+        game.players[1].ship.rot += PI;
+        let fire = ShipInput {
+            fire: true,
+            ..Default::default()
+        };
+        game.update(1.0 / 60.0, &[fire, fire]);
+        assert_eq!(game.saucers[0].shots, [2, 1]);
+
+        let mut kill = None;
+        for _ in 0..60 {
+            game.update(1.0 / 60.0, &[]);
+            kill = kill.or(game.take_events().into_iter().find_map(|e| match e {
+                GameEvent::SaucerDestroyed { by, kill, .. } => Some((by, kill)),
+                _ => None,
+            }));
+        }
+        assert_eq!(kill, Some((Some(0), Some(Kill::Shot { shots: 2 }))));
+    }
+
+    fn quiet_game() -> Game {
+        rand::srand(1);
+        let mut game = Game::new(1, Vec2::new(800.0, 600.0));
+        // nothing about to run into the ship, and no more on the way
+        game.asteroids.clear();
+        game.asteroids_spawned_in_wave = game.get_wave_asteroid_amount();
+        game
+    }
+
+    #[test]
+    fn ships_respawn_protected_once_the_centre_is_clear() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = quiet_game();
+        let centre = game.players[0].ship.pos;
+        game.asteroids
+            .push(Asteroid::new(AsteroidType::Large, centre, Vec2::ZERO));
+        game.destroy_ship(0, DeathCause::Saucer);
+        assert!(game.players[0].ship.state.is_destroyed());
+        assert!(!game.debris.is_empty());
+
+        // held back by the asteroid sitting in the middle
+        for _ in 0..((SHIP_RESPAWN_DELAY + 1.0) * 60.0) as usize {
+            game.update(1.0 / 60.0, &[]);
+        }
+        assert!(game.players[0].ship.state.is_destroyed());
+        game.asteroids.clear();
+        game.update(1.0 / 60.0, &[]);
+        let ship = &game.players[0].ship;
+        assert!(matches!(ship.state, ShipState::Shielded));
+        assert_eq!(ship.pos, centre);
+
+        // nothing can hurt it for a moment
+        let shot = Bullet {
+            pos: centre,
+            vel: Vec2::ZERO,
+            collided: false,
+        };
+        game.saucer_bullets.push((shot, 0.0));
+        game.update(1.0 / 60.0, &[]);
+        assert!(matches!(game.players[0].ship.state, ShipState::Shielded));
+        game.saucer_bullets.clear();
+        for _ in 0..(SHIP_SPAWN_PROTECTION_TIME * 60.0) as usize {
+            game.update(1.0 / 60.0, &[]);
+        }
+        assert!(matches!(game.players[0].ship.state, ShipState::Normal));
+    }
+
+    #[test]
+    fn casual_ships_fly_on_shielded() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = Game {
+            death_mode: DeathMode::Casual,
+            ..quiet_game()
+        };
+        let (pos, vel) = (Vec2::new(100.0, 200.0), Vec2::new(30.0, -40.0));
+        game.players[0].ship.pos = pos;
+        game.players[0].ship.vel = vel;
+        let lives = game.players[0].lives.remaining();
+
+        game.destroy_ship(0, DeathCause::Saucer);
+        let ship = &game.players[0].ship;
+        assert!(matches!(ship.state, ShipState::Shielded));
+        assert_eq!((ship.pos, ship.vel), (pos, vel));
+        assert!(game.debris.is_empty());
+        assert_eq!(game.players[0].lives.remaining(), lives - 1);
+
+        // the last life still ends it
+        while !game.players[0].out_of_lives {
+            game.destroy_ship(0, DeathCause::Saucer);
+        }
+        assert!(game.players[0].ship.state.is_destroyed());
+    }
+
+    #[test]
+    fn combos_build_up_to_the_limit() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = quiet_game();
+        for _ in 0..COMBO_HITS_PER_MULTIPLIER {
+            game.award_kill(0, 100, Vec2::ZERO);
+        }
+        assert_eq!(game.players[0].score, 100 * COMBO_HITS_PER_MULTIPLIER);
+        game.award_kill(0, 100, Vec2::ZERO);
+        assert_eq!(game.players[0].score, 100 * COMBO_HITS_PER_MULTIPLIER + 200);
+        for _ in 0..COMBO_HITS_PER_MULTIPLIER * COMBO_MAX_MULTIPLIER {
+            game.award_kill(0, 100, Vec2::ZERO);
+        }
+        assert_eq!(game.players[0].combo_multiplier(), COMBO_MAX_MULTIPLIER);
+    }
+
+    #[test]
+    fn losing_the_ship_breaks_the_combo() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = quiet_game();
+        game.award_kill(0, 100, Vec2::ZERO);
+        game.destroy_ship(0, DeathCause::Saucer);
+        assert_eq!(game.players[0].combo, 0);
+    }
+
+    #[test]
+    fn ramming_an_asteroid_leaves_no_combo() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = quiet_game();
+        game.players[0].ship.state = ShipState::Normal;
+        game.award_kill(0, 100, Vec2::ZERO);
+        let pos = game.players[0].ship.pos;
+        game.asteroids
+            .push(Asteroid::new(AsteroidType::Large, pos, Vec2::ZERO));
+        game.update(1.0 / 60.0, &[]);
+
+        assert!(game.players[0].ship.state.is_destroyed());
+        assert!(game.players[0].score > 100);
+        assert_eq!(game.players[0].combo, 0);
+    }
+
+    #[test]
+    fn misses_and_pauses_break_the_combo() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = quiet_game();
+
+        // a bullet running out without hitting anything
+        game.award_kill(0, 100, Vec2::ZERO);
+        let miss = Bullet {
+            pos: Vec2::new(100.0, 100.0),
+            vel: Vec2::ZERO,
+            collided: false,
+        };
+        game.players[0].bullets.push((miss, BULLET_TTL));
+        game.update(1.0 / 60.0, &[]);
+        assert_eq!(game.players[0].combo, 0);
+        assert!(game.players[0].bullets.is_empty());
+
+        // going too long between kills
+        game.award_kill(0, 100, Vec2::ZERO);
+        for _ in 0..(COMBO_WINDOW * 60.0) as usize + 1 {
+            game.update(1.0 / 60.0, &[]);
+        }
+        assert_eq!(game.players[0].combo, 0);
+    }
+
+    #[test]
+    fn clearing_a_wave_quickly_and_safely_earns_bonuses() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = quiet_game();
+        game.update(1.0 / 60.0, &[]);
+        assert_eq!(
+            game.players[0].score,
+            WAVE_QUICK_CLEAR_BONUS + WAVE_NO_DEATH_BONUS
+        );
+        assert_eq!(game.asteroid_wave, 1);
+
+        let mut game = quiet_game();
+        game.wave_time = WAVE_QUICK_CLEAR_TIME;
+        game.update(1.0 / 60.0, &[]);
+        assert_eq!(game.players[0].score, WAVE_NO_DEATH_BONUS);
+
+        let mut game = quiet_game();
+        game.wave_deaths = 1;
+        game.update(1.0 / 60.0, &[]);
+        assert_eq!(game.players[0].score, WAVE_QUICK_CLEAR_BONUS);
+
+        let mut game = quiet_game();
+        game.wave_time = WAVE_QUICK_CLEAR_TIME;
+        game.wave_deaths = 1;
+        game.update(1.0 / 60.0, &[]);
+        assert_eq!(game.players[0].score, 0);
+    }
+
+    fn quiet_deathmatch() -> Game {
+        rand::srand(1);
+        let mut game = Game::new_deathmatch(2, Vec2::new(800.0, 600.0));
+        // the next asteroids are a while off yet
+        game.asteroids.clear();
+        game
+    }
+
+    #[test]
+    fn deathmatch_kills_go_to_whoever_fired() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = quiet_deathmatch();
+        game.players[1].ship.state = ShipState::Normal;
+        let bullet = Bullet {
+            pos: game.players[1].ship.pos,
+            vel: Vec2::ZERO,
+            collided: false,
+        };
+        game.players[0].bullets.push((bullet, 0.0));
+        game.update(1.0 / 60.0, &[]);
+
+        assert!(game.players[1].ship.state.is_destroyed());
+        assert_eq!(game.players[0].kills, 1);
+        assert_eq!(game.players[0].score, DEATHMATCH_KILL_SCORE);
+        assert_eq!(game.players[1].kills, 0);
+        assert!(!game.game_over);
+    }
+
+    #[test]
+    fn deathmatches_end_at_the_kill_or_time_limit() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = quiet_deathmatch();
+        game.players[1].kills = DEATHMATCH_KILL_LIMIT;
+        game.update(1.0 / 60.0, &[]);
+        assert!(game.game_over);
+        assert_eq!(game.match_winner(), Some(1));
+
+        let mut game = quiet_deathmatch();
+        game.players[0].kills = 3;
+        game.players[1].kills = 2;
+        game.match_time = DEATHMATCH_TIME_LIMIT - 1.0;
+        game.update(1.0 / 60.0, &[]);
+        assert!(!game.game_over);
+        game.match_time = DEATHMATCH_TIME_LIMIT;
+        game.update(1.0 / 60.0, &[]);
+        assert!(game.game_over);
+        assert_eq!(game.match_winner(), Some(0));
+
+        // a shared lead is a draw
+        game.players[1].kills = 3;
+        assert_eq!(game.match_winner(), None);
+    }
+
+    #[test]
+    fn hyperspace_failure_chances() {
+        let shot = || Bullet {
+            pos: Vec2::ZERO,
+            vel: Vec2::ZERO,
+            collided: false,
+        };
+        let mut game = Game {
+            saucer_bullets: (0..100).map(|_| (shot(), 0.0)).collect(),
+            ..Default::default()
+        };
+
+        // modern and safe jumps never fail
+        assert_eq!(game.hyperspace_failure_chance(), 0.0);
+        game.hyperspace_rules = HyperspaceRules::Safe;
+        assert_eq!(game.hyperspace_failure_chance(), 0.0);
+
+        // arcade jumps fail more often the busier the playfield, up to a limit
+        game.hyperspace_rules = HyperspaceRules::Arcade;
+        let busy = game.hyperspace_failure_chance();
+        game.saucer_bullets.truncate(1);
+        let quiet = game.hyperspace_failure_chance();
+        assert!(
+            (quiet - ARCADE_HYPERSPACE_BASE_FAILURE - ARCADE_HYPERSPACE_FAILURE_PER_OBJECT).abs()
+                < 1e-6
+        );
+        assert!(busy > quiet);
+        assert_eq!(busy, ARCADE_HYPERSPACE_MAX_FAILURE);
+        game.saucer_bullets.clear();
+        assert_eq!(
+            game.hyperspace_failure_chance(),
+            ARCADE_HYPERSPACE_BASE_FAILURE
+        );
+    }
+
+    #[test]
+    fn hyperspace_destinations() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = quiet_game();
+        let from = game.bounds / 2.0;
+
+        // modern jumps always go somewhere else
+        for _ in 0..100 {
+            let to = game.hyperspace_destination(from);
+            assert!(from.distance(to) > SHIP_HYPERSPACE_MIN_DISTANCE);
+        }
+
+        // safe jumps land as far from the threats as the grid allows, here in the one clear
+        // corner of a playfield full of asteroids
+        game.hyperspace_rules = HyperspaceRules::Safe;
+        for x in 0..8 {
+            for y in 0..6 {
+                let pos = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * 100.0;
+                if pos.x > 600.0 && pos.y > 400.0 {
+                    continue;
+                }
+                game.asteroids
+                    .push(Asteroid::new(AsteroidType::Small, pos, Vec2::ZERO));
+            }
+        }
+        for _ in 0..20 {
+            let to = game.hyperspace_destination(from);
+            assert!(to.x > 600.0 && to.y > 400.0, "landed at {to}");
+            assert!(game.threat_distance(to) > SHIP_COLLISION_RADIUS);
+        }
+    }
+
+    #[test]
+    fn arcade_hyperspace_fails_about_as_often_as_it_should() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut failures = 0;
+        for seed in 0..1000 {
+            let mut game = quiet_game();
+            game.hyperspace_rules = HyperspaceRules::Arcade;
+            // keeps the wave going without getting in the way
+            game.asteroids.push(Asteroid::new(
+                AsteroidType::Small,
+                Vec2::new(20.0, 20.0),
+                Vec2::ZERO,
+            ));
+            game.players[0].ship.state = ShipState::Normal;
+            game.players[0].last_hyperspace = SHIP_HYPERSPACE_FREQUENCY;
+            let jump = ShipInput {
+                hyperspace: true,
+                ..Default::default()
+            };
+            rand::srand(seed);
+            game.update(1.0 / 60.0, &[jump]);
+            if !game.players[0].ship.hyperspace_malfunction {
+                continue;
+            }
+            failures += 1;
+            // a failed jump destroys the ship once it lands
+            while game.players[0].ship.state.is_translating() {
+                game.update(1.0 / 60.0, &[]);
+            }
+            assert!(game.players[0].ship.state.is_destroyed());
+        }
+        let expected =
+            (ARCADE_HYPERSPACE_BASE_FAILURE + ARCADE_HYPERSPACE_FAILURE_PER_OBJECT) * 1000.0;
+        assert!(
+            (failures as f32 - expected).abs() < expected / 2.0,
+            "{failures} failures"
+        );
+    }
+
+    #[test]
+    fn the_shield_can_take_the_hyperspace_key() {
+        let controls = PLAYER_CONTROLS[0];
+        let hyperspace_key = |key: KeyCode| key == controls.hyperspace;
+        let shield_key = |key: KeyCode| key == controls.shield;
+
+        let input = controls.read_with(ShipAbility::Shield, hyperspace_key, hyperspace_key);
+        assert!(input.shield && !input.hyperspace);
+        let input = controls.read_with(ShipAbility::Shield, shield_key, shield_key);
+        assert!(!input.shield);
+
+        let input = controls.read_with(
+            ShipAbility::HyperspaceAndShield,
+            hyperspace_key,
+            hyperspace_key,
+        );
+        assert!(input.hyperspace && !input.shield);
+        let input = controls.read_with(ShipAbility::HyperspaceAndShield, shield_key, shield_key);
+        assert!(input.shield && !input.hyperspace);
+
+        let input = controls.read_with(ShipAbility::Hyperspace, shield_key, shield_key);
+        assert!(!input.shield);
+    }
+
+    #[test]
+    fn energy_shield_bounces_things_off() {
+        let mut ship = Ship {
+            pos: Vec2::new(100.0, 100.0),
+            energy_shield_active: true,
+            energy_shield_energy: ENERGY_SHIELD_MAX_ENERGY,
+            ..Default::default()
+        };
+        let radius = AsteroidType::Medium.size();
+
+        // coming in from the right at an angle
+        let mut pos = ship.pos + Vec2::new(ENERGY_SHIELD_RADIUS, 0.0);
+        let mut vel = Vec2::new(-50.0, 20.0);
+        assert!(ship.deflect(&mut pos, &mut vel, radius));
+        assert_eq!(
+            pos,
+            ship.pos + Vec2::new(ENERGY_SHIELD_RADIUS + radius, 0.0)
+        );
+        assert_eq!(vel, Vec2::new(50.0, 20.0));
+        assert_eq!(
+            ship.energy_shield_energy,
+            ENERGY_SHIELD_MAX_ENERGY - ENERGY_SHIELD_BOUNCE_COST
+        );
+
+        // already heading away, so only pushed out
+        let mut pos = ship.pos - Vec2::new(0.0, radius);
+        let mut vel = Vec2::new(0.0, -30.0);
+        assert!(ship.deflect(&mut pos, &mut vel, radius));
+        assert_eq!(
+            pos,
+            ship.pos - Vec2::new(0.0, ENERGY_SHIELD_RADIUS + radius)
+        );
+        assert_eq!(vel, Vec2::new(0.0, -30.0));
+
+        // out of reach, or with the shield down
+        let mut pos = ship.pos + Vec2::new(ENERGY_SHIELD_RADIUS + radius + 1.0, 0.0);
+        let mut vel = Vec2::new(-50.0, 0.0);
+        assert!(!ship.deflect(&mut pos, &mut vel, radius));
+        ship.energy_shield_active = false;
+        let mut pos = ship.pos;
+        assert!(!ship.deflect(&mut pos, &mut vel, radius));
+        assert_eq!(vel, Vec2::new(-50.0, 0.0));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{INITIAL_LIVES, LIFE_SCORE};
+
+/// Changes to the player's lives, for the game to react to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifeEvent {
+    LifeLost,
+    ExtraLife,
+    GameOver,
+}
+
+/// Tracks the ships the player has left, including the one currently in play
+#[derive(Clone)]
+pub struct Lives {
+    remaining: usize,
+    // number of extra lives already awarded from score
+    awarded: usize,
+    life_score: usize,
+}
+
+impl Default for Lives {
+    fn default() -> Self {
+        Self::new(INITIAL_LIVES, LIFE_SCORE)
+    }
+}
+
+impl Lives {
+    pub fn new(initial: usize, life_score: usize) -> Self {
+        Self {
+            remaining: initial,
+            awarded: 0,
+            life_score,
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    pub fn set_remaining(&mut self, remaining: usize) {
+        self.remaining = remaining;
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Takes away the ship in play, ending the game if it was the last one
+    pub fn lose(&mut self) -> Vec<LifeEvent> {
+        if self.is_game_over() {
+            return Vec::new();
+        }
+
+        self.remaining -= 1;
+        if self.is_game_over() {
+            vec![LifeEvent::LifeLost, LifeEvent::GameOver]
+        } else {
+            vec![LifeEvent::LifeLost]
+        }
+    }
+
+    /// Awards an extra life for every score threshold crossed since the last update
+    pub fn update_score(&mut self, score: usize) -> Vec<LifeEvent> {
+        if self.is_game_over() {
+            return Vec::new();
+        }
+
+        let earned = score / self.life_score;
+        let new_lives = earned.saturating_sub(self.awarded);
+        self.awarded += new_lives;
+        self.remaining += new_lives;
+        vec![LifeEvent::ExtraLife; new_lives]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_over_after_initial_lives_are_lost() {
+        let mut lives = Lives::new(3, 10_000);
+
+        assert_eq!(lives.lose(), vec![LifeEvent::LifeLost]);
+        assert_eq!(lives.lose(), vec![LifeEvent::LifeLost]);
+        assert_eq!(lives.lose(), vec![LifeEvent::LifeLost, LifeEvent::GameOver]);
+        assert!(lives.is_game_over());
+        assert_eq!(lives.lose(), vec![]);
+        assert_eq!(lives.remaining(), 0);
+    }
+
+    #[test]
+    fn extra_life_awarded_once_per_threshold() {
+        let mut lives = Lives::new(3, 10_000);
+
+        assert_eq!(lives.update_score(9_999), vec![]);
+        assert_eq!(lives.update_score(10_000), vec![LifeEvent::ExtraLife]);
+        assert_eq!(lives.update_score(15_000), vec![]);
+        assert_eq!(lives.remaining(), 4);
+    }
+
+    #[test]
+    fn several_thresholds_crossed_in_one_update() {
+        let mut lives = Lives::new(3, 10_000);
+
+        assert_eq!(lives.update_score(35_000), vec![LifeEvent::ExtraLife; 3]);
+        assert_eq!(lives.remaining(), 6);
+        assert_eq!(lives.update_score(40_000), vec![LifeEvent::ExtraLife]);
+        assert_eq!(lives.remaining(), 7);
+    }
+
+    #[test]
+    fn no_extra_lives_after_game_over() {
+        let mut lives = Lives::new(1, 10_000);
+
+        lives.lose();
+        assert_eq!(lives.update_score(20_000), vec![]);
+        assert!(lives.is_game_over());
+    }
+}
@@ -0,0 +1,110 @@
+//! Stereo mixing of mono voices, each with its own pan, gain and playback rate
+
+use std::f32::consts::FRAC_PI_4;
+
+/// A mono sound placed in the stereo field
+#[derive(Clone, Copy)]
+pub struct Voice<'a> {
+    pub samples: &'a [f32],
+    /// From -1 for hard left to 1 for hard right
+    pub pan: f32,
+    pub gain: f32,
+    /// How fast the samples are played back, above one sounds higher and shorter
+    pub rate: f32,
+}
+
+impl<'a> Voice<'a> {
+    pub fn new(samples: &'a [f32]) -> Self {
+        Self {
+            samples,
+            pan: 0.0,
+            gain: 1.0,
+            rate: 1.0,
+        }
+    }
+
+    /// Frames it lasts once played back at its rate
+    pub fn len(&self) -> usize {
+        (self.samples.len() as f32 / self.rate).ceil() as usize
+    }
+
+    /// The sample at a fractional position, blending its neighbours
+    fn sample_at(&self, position: f32) -> f32 {
+        let i = position as usize;
+        let t = position.fract();
+        let a = self.samples.get(i).copied().unwrap_or(0.0);
+        let b = self.samples.get(i + 1).copied().unwrap_or(0.0);
+        a + (b - a) * t
+    }
+}
+
+/// Left and right gains that keep the loudness even across the field
+pub fn pan_gains(pan: f32) -> (f32, f32) {
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+/// Mixes the voices into interleaved left and right samples, long enough for the longest
+pub fn mix(voices: &[Voice]) -> Vec<f32> {
+    let frames = voices.iter().map(Voice::len).max().unwrap_or(0);
+    let mut out = vec![0.0; frames * 2];
+    for voice in voices {
+        let (left, right) = pan_gains(voice.pan);
+        for (frame, out) in out.chunks_exact_mut(2).take(voice.len()).enumerate() {
+            let sample = voice.sample_at(frame as f32 * voice.rate) * voice.gain;
+            out[0] += sample * left;
+            out[1] += sample * right;
+        }
+    }
+    out.iter_mut().for_each(|s| *s = s.clamp(-1.0, 1.0));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn energy(samples: impl Iterator<Item = f32>) -> f32 {
+        samples.map(|s| s * s).sum()
+    }
+
+    #[test]
+    fn panning_moves_the_sound_between_channels() {
+        let samples = vec![0.5; 100];
+        let hard_left = mix(&[Voice {
+            pan: -1.0,
+            ..Voice::new(&samples)
+        }]);
+        assert!(hard_left
+            .chunks(2)
+            .all(|f| f[0] > 0.49 && f[1].abs() < 1e-6));
+
+        let centre = mix(&[Voice::new(&samples)]);
+        assert!(centre.chunks(2).all(|f| (f[0] - f[1]).abs() < 1e-6));
+        // equal power, so a centred sound is as loud overall as a hard panned one
+        let total = |mixed: &[f32]| energy(mixed.iter().copied());
+        assert!((total(&centre) - total(&hard_left)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn voices_are_scaled_resampled_and_summed() {
+        let ramp: Vec<f32> = (0..100).map(|i| i as f32 / 100.0).collect();
+        let fast = Voice {
+            rate: 2.0,
+            gain: 0.5,
+            ..Voice::new(&ramp)
+        };
+        let quiet = Voice {
+            gain: 0.1,
+            ..Voice::new(&ramp)
+        };
+        let mixed = mix(&[fast, quiet]);
+        assert_eq!(mixed.len(), 100 * 2);
+
+        let (left, _) = pan_gains(0.0);
+        // the faster voice runs through the ramp twice as quickly, finishing halfway
+        let expected = (0.8 * 0.5 + 0.4 * 0.1) * left;
+        assert!((mixed[40 * 2] - expected).abs() < 1e-4);
+        assert!((mixed[80 * 2] - 0.8 * 0.1 * left).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,457 @@
+//! Online play, every peer runs the whole game and only inputs cross the network
+
+use std::collections::BTreeMap;
+
+use asteroids_net::Message;
+use macroquad::{prelude::*, rand};
+
+use crate::{
+    events::GameEvent,
+    render::{Frame, Renderer, Screen},
+    rollback::Rollback,
+    screen_bounds, Game, ShipInput, View, BACKGROUND_COLOR, NET_INPUT_DELAY, NET_MAX_INPUT_BATCH,
+    NET_MAX_TICKS_PER_FRAME, NET_PLAYFIELD, NET_TICK,
+};
+
+/// A connection to the relay
+pub trait Transport {
+    fn send(&mut self, message: &Message);
+    /// Messages received since the last call, none once the connection has dropped
+    fn receive(&mut self) -> Option<Vec<Message>>;
+}
+
+/// WebSocket connection to the relay for native builds
+#[cfg(not(target_arch = "wasm32"))]
+pub struct RelayTransport(Option<asteroids_net::client::Client>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RelayTransport {
+    pub fn connect(url: &str) -> Self {
+        match asteroids_net::client::Client::connect(url) {
+            Ok(client) => Self(Some(client)),
+            Err(e) => {
+                eprintln!("failed to connect to relay at {url}: {e}");
+                Self(None)
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for RelayTransport {
+    fn send(&mut self, message: &Message) {
+        if let Some(client) = &mut self.0 {
+            if client.send(message).is_err() {
+                self.0 = None;
+            }
+        }
+    }
+
+    fn receive(&mut self) -> Option<Vec<Message>> {
+        let messages = self.0.as_mut()?.receive();
+        if messages.is_err() {
+            self.0 = None;
+        }
+        messages.ok()
+    }
+}
+
+// implemented by web/net.js, which has to be loaded alongside the miniquad bundle
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    fn asteroids_net_connect(url: *const u8, len: usize) -> i32;
+    fn asteroids_net_send(socket: i32, data: *const u8, len: usize);
+    /// Copies the next message into the buffer returning its length, 0 if none waiting and -1 once closed
+    fn asteroids_net_receive(socket: i32, buffer: *mut u8, len: usize) -> i32;
+}
+
+/// WebSocket connection to the relay through the browser
+#[cfg(target_arch = "wasm32")]
+pub struct RelayTransport(i32);
+
+#[cfg(target_arch = "wasm32")]
+impl RelayTransport {
+    pub fn connect(url: &str) -> Self {
+        Self(unsafe { asteroids_net_connect(url.as_ptr(), url.len()) })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Transport for RelayTransport {
+    fn send(&mut self, message: &Message) {
+        let data = message.encode();
+        unsafe { asteroids_net_send(self.0, data.as_ptr(), data.len()) }
+    }
+
+    fn receive(&mut self) -> Option<Vec<Message>> {
+        let mut messages = Vec::new();
+        let mut buffer = [0; 64];
+        loop {
+            match unsafe { asteroids_net_receive(self.0, buffer.as_mut_ptr(), buffer.len()) } {
+                -1 => return None,
+                0 => return Some(messages),
+                len => messages.extend(Message::decode(&buffer[..len as usize])),
+            }
+        }
+    }
+}
+
+/// What the players online are trying to do to each other
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OnlineMode {
+    Coop,
+    Deathmatch,
+}
+
+impl OnlineMode {
+    fn room(self) -> &'static str {
+        match self {
+            Self::Coop => "coop",
+            Self::Deathmatch => "deathmatch",
+        }
+    }
+}
+
+pub struct NetSession {
+    transport: Box<dyn Transport>,
+    mode: OnlineMode,
+    local_player: usize,
+    // none until the relay has found everyone and started the game
+    rollback: Option<Rollback>,
+    disconnected: bool,
+    // next tick the local input is read for
+    input_tick: u32,
+    // local inputs the other peers may not have yet, by tick
+    local_inputs: BTreeMap<u32, u8>,
+    // first tick of our inputs each player is still missing
+    acks: Vec<u32>,
+    // our checksums and the other peers', by tick
+    checksums: BTreeMap<u32, u64>,
+    remote_checksums: BTreeMap<(u32, usize), u64>,
+    // ticks our game was found to differ from someone else's
+    desyncs: Vec<u32>,
+    accumulator: f32,
+}
+
+impl NetSession {
+    pub fn new(mode: OnlineMode, mut transport: Box<dyn Transport>) -> Self {
+        transport.send(&Message::Join {
+            room: mode.room().to_string(),
+            players: 2,
+        });
+        Self {
+            transport,
+            mode,
+            local_player: 0,
+            rollback: None,
+            disconnected: false,
+            input_tick: NET_INPUT_DELAY,
+            local_inputs: BTreeMap::new(),
+            acks: Vec::new(),
+            checksums: BTreeMap::new(),
+            remote_checksums: BTreeMap::new(),
+            desyncs: Vec::new(),
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn game(&self) -> Option<&Game> {
+        self.rollback.as_ref().map(Rollback::game)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.disconnected || self.rollback.as_ref().is_some_and(Rollback::is_over)
+    }
+
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        self.rollback
+            .as_mut()
+            .map(Rollback::take_events)
+            .unwrap_or_default()
+    }
+
+    fn handle(&mut self, message: Message) {
+        match message {
+            Message::Welcome { player } => self.local_player = player as usize,
+            Message::Start { seed, players } => {
+                let players = players as usize;
+                rand::srand(seed);
+                let mut game = match self.mode {
+                    OnlineMode::Coop => Game::new(players, NET_PLAYFIELD),
+                    OnlineMode::Deathmatch => Game::new_deathmatch(players, NET_PLAYFIELD),
+                };
+                game.bullet_lead = NET_INPUT_DELAY as f32 * NET_TICK;
+                let mut rollback = Rollback::new(game, seed);
+                // nobody has pressed anything in the ticks before the first inputs arrive
+                for tick in 0..NET_INPUT_DELAY {
+                    for player in 0..players {
+                        rollback.add_input(player, tick, ShipInput::default());
+                    }
+                }
+                self.rollback = Some(rollback);
+                self.acks = vec![NET_INPUT_DELAY; players];
+            }
+            Message::Input {
+                player,
+                ack,
+                tick,
+                inputs,
+            } => {
+                let player = player as usize;
+                let Some(rollback) = &mut self.rollback else {
+                    return;
+                };
+                if player >= self.acks.len() || player == self.local_player {
+                    return;
+                }
+                self.acks[player] = self.acks[player].max(ack);
+                for (offset, input) in inputs.into_iter().enumerate() {
+                    rollback.add_input(player, tick + offset as u32, ShipInput::from_bits(input));
+                }
+            }
+            Message::Checksum {
+                player,
+                tick,
+                checksum,
+            } => {
+                self.remote_checksums
+                    .insert((tick, player as usize), checksum);
+                self.check_sync(tick);
+            }
+            Message::PeerLeft { .. } => self.disconnected = true,
+            Message::Join { .. } => {}
+        }
+    }
+
+    /// Compares our checksum for the tick with everyone else's, logging any difference
+    fn check_sync(&mut self, tick: u32) {
+        let Some(&local) = self.checksums.get(&tick) else {
+            return;
+        };
+        for (&(_, player), &remote) in self.remote_checksums.range((tick, 0)..=(tick, usize::MAX)) {
+            if remote != local {
+                eprintln!(
+                    "desync at tick {tick}: our checksum {local:016x}, player {}'s {remote:016x}",
+                    player + 1
+                );
+                self.desyncs.push(tick);
+            }
+        }
+    }
+
+    /// Reads the local input ahead of time and simulates as far as the rollback window allows
+    pub fn update(&mut self, delta_t: f32, mut input: ShipInput) {
+        match self.transport.receive() {
+            Some(messages) => messages.into_iter().for_each(|m| self.handle(m)),
+            None => self.disconnected = true,
+        }
+        if self.disconnected {
+            return;
+        }
+        let Some(rollback) = &mut self.rollback else {
+            return;
+        };
+        rollback.resimulate();
+
+        // a stalled peer leaves time owed, capped so the catch up doesn't freeze the game
+        self.accumulator =
+            (self.accumulator + delta_t).min(NET_TICK * NET_MAX_TICKS_PER_FRAME as f32);
+        while self.accumulator >= NET_TICK {
+            if self.input_tick <= rollback.tick() + NET_INPUT_DELAY {
+                rollback.add_input(self.local_player, self.input_tick, input);
+                self.local_inputs.insert(self.input_tick, input.to_bits());
+                self.input_tick += 1;
+                // a hyperspace press only counts for the first tick it is read for
+                input.hyperspace = false;
+            }
+
+            if !rollback.can_advance() {
+                break;
+            }
+            rollback.advance();
+            self.accumulator -= NET_TICK;
+        }
+
+        // keep sending whatever hasn't been acknowledged, in case it was lost
+        let others = (0..self.acks.len()).filter(|&p| p != self.local_player);
+        let from = others
+            .clone()
+            .map(|p| self.acks[p])
+            .min()
+            .unwrap_or(self.input_tick);
+        self.local_inputs.retain(|&tick, _| tick >= from);
+        if !self.local_inputs.is_empty() {
+            let ack = others.map(|p| rollback.next_missing(p)).min().unwrap_or(0);
+            self.transport.send(&Message::Input {
+                player: self.local_player as u8,
+                ack,
+                tick: from,
+                inputs: self
+                    .local_inputs
+                    .values()
+                    .take(NET_MAX_INPUT_BATCH as usize)
+                    .copied()
+                    .collect(),
+            });
+        }
+
+        for (tick, checksum) in rollback.take_checksums() {
+            self.transport.send(&Message::Checksum {
+                player: self.local_player as u8,
+                tick,
+                checksum,
+            });
+            self.checksums.insert(tick, checksum);
+            self.check_sync(tick);
+        }
+    }
+
+    pub fn draw(&self, view: &View) {
+        match self.game() {
+            Some(game) => game.draw(view),
+            None => Screen.render(&lobby_frame(screen_bounds())),
+        }
+    }
+}
+
+/// Shown until the relay has found everyone
+fn lobby_frame(screen: Vec2) -> Frame {
+    let mut frame = Frame::default();
+    frame.rectangle(0.0, 0.0, screen.x, screen.y, BACKGROUND_COLOR);
+    frame.centered_text("Waiting for another player...", screen / 2.0, 32.0, WHITE);
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+    use super::*;
+    use crate::rollback::tests::{scripted_input, SIMULATION};
+
+    /// Stands in for the relay, holding messages back and losing some of them
+    struct LoopbackNetwork {
+        frame: u32,
+        latency: u32,
+        loss: f32,
+        random: u64,
+        // messages waiting for each player, with the frame they arrive on
+        queues: Vec<VecDeque<(u32, Message)>>,
+    }
+
+    impl LoopbackNetwork {
+        fn lose_packet(&mut self) -> bool {
+            self.random ^= self.random << 13;
+            self.random ^= self.random >> 7;
+            self.random ^= self.random << 17;
+            (self.random % 1000) as f32 / 1000.0 < self.loss
+        }
+    }
+
+    struct LoopbackTransport {
+        network: Rc<RefCell<LoopbackNetwork>>,
+        player: usize,
+    }
+
+    impl Transport for LoopbackTransport {
+        fn send(&mut self, message: &Message) {
+            let mut network = self.network.borrow_mut();
+            if matches!(message, Message::Join { .. }) || network.lose_packet() {
+                return;
+            }
+            let arrival = network.frame + network.latency;
+            network
+                .queues
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| *i != self.player)
+                .for_each(|(_, queue)| queue.push_back((arrival, message.clone())));
+        }
+
+        fn receive(&mut self) -> Option<Vec<Message>> {
+            let mut network = self.network.borrow_mut();
+            let frame = network.frame;
+            let queue = &mut network.queues[self.player];
+            let mut messages = Vec::new();
+            while queue.front().is_some_and(|(arrival, _)| *arrival <= frame) {
+                messages.push(queue.pop_front().unwrap().1);
+            }
+            Some(messages)
+        }
+    }
+
+    /// Runs two peers over the loopback network, returning them once both have caught up
+    fn play(latency: u32, loss: f32, frames: u32) -> Vec<NetSession> {
+        let network = Rc::new(RefCell::new(LoopbackNetwork {
+            frame: 0,
+            latency,
+            loss,
+            random: 0x2545_f491_4f6c_dd1d,
+            queues: vec![VecDeque::new(); 2],
+        }));
+        let mut sessions: Vec<NetSession> = (0..2)
+            .map(|player| {
+                network.borrow_mut().queues[player].extend([
+                    (
+                        0,
+                        Message::Welcome {
+                            player: player as u8,
+                        },
+                    ),
+                    (
+                        0,
+                        Message::Start {
+                            seed: 42,
+                            players: 2,
+                        },
+                    ),
+                ]);
+                let transport = LoopbackTransport {
+                    network: network.clone(),
+                    player,
+                };
+                NetSession::new(OnlineMode::Deathmatch, Box::new(transport))
+            })
+            .collect();
+
+        // the last stretch runs without losses so everything gets through in the end
+        for frame in 0..frames + 120 {
+            if frame == frames {
+                network.borrow_mut().loss = 0.0;
+            }
+            for (player, session) in sessions.iter_mut().enumerate() {
+                session.update(NET_TICK, scripted_input(player as u64 + 1, frame));
+            }
+            network.borrow_mut().frame += 1;
+        }
+        sessions
+    }
+
+    #[test]
+    fn peers_stay_in_sync_through_latency_and_packet_loss() {
+        let _lock = SIMULATION.lock().unwrap();
+        let sessions = play(5, 0.25, 1200);
+
+        for session in &sessions {
+            assert!(!session.disconnected);
+            assert!(session.desyncs.is_empty());
+            assert!(session.rollback.as_ref().unwrap().tick() > 1000);
+        }
+        let (first, second) = (&sessions[0].checksums, &sessions[1].checksums);
+        assert!(first.len() > 10);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn differing_checksums_are_reported_as_desyncs() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut sessions = play(2, 0.0, 120);
+        let session = &mut sessions[0];
+        let (&tick, &checksum) = session.checksums.iter().next().unwrap();
+
+        session.remote_checksums.insert((tick, 1), checksum ^ 1);
+        session.check_sync(tick);
+
+        assert_eq!(session.desyncs, [tick]);
+    }
+}
@@ -0,0 +1,105 @@
+//! Colour schemes for the playfield, the monochrome ones mimicking old vector monitors
+
+use macroquad::{
+    miniquad::{BlendFactor, BlendState, BlendValue, Equation},
+    prelude::*,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    // everything in its own colour
+    Classic,
+    Green,
+    Amber,
+    White,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [Self::Classic, Self::Green, Self::Amber, Self::White];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::Green => "green",
+            Self::Amber => "amber",
+            Self::White => "white",
+        }
+    }
+
+    /// The one colour everything is drawn in, none to keep the colours as they are
+    pub fn tint(&self) -> Option<Vec3> {
+        match self {
+            Self::Classic => None,
+            Self::Green => Some(Vec3::new(0.2, 1.0, 0.3)),
+            Self::Amber => Some(Vec3::new(1.0, 0.7, 0.1)),
+            Self::White => Some(Vec3::ONE),
+        }
+    }
+}
+
+const VERTEX_SHADER: &str = "#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+";
+
+// keeps how bright each colour is, dropping its hue for the tint
+const FRAGMENT_SHADER: &str = "#version 100
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+uniform lowp vec3 tint;
+
+void main() {
+    lowp vec4 drawn = color * texture2D(Texture, uv);
+    lowp float brightness = max(drawn.r, max(drawn.g, drawn.b));
+    gl_FragColor = vec4(tint * brightness, drawn.a);
+}
+";
+
+/// Material that draws in the palette's tint, none for the classic colours or if the shader
+/// can't be built, which leaves everything in its own colour
+pub fn material(palette: Palette) -> Option<Material> {
+    let tint = palette.tint()?;
+    let material = load_material(
+        ShaderSource::Glsl {
+            vertex: VERTEX_SHADER,
+            fragment: FRAGMENT_SHADER,
+        },
+        MaterialParams {
+            pipeline_params: PipelineParams {
+                color_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::Value(BlendValue::SourceAlpha),
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                ..Default::default()
+            },
+            uniforms: vec![UniformDesc::new("tint", UniformType::Float3)],
+            ..Default::default()
+        },
+    );
+    match material {
+        Ok(material) => {
+            material.set_uniform("tint", tint);
+            Some(material)
+        }
+        Err(e) => {
+            eprintln!("can't build the {} palette: {e}", palette.name());
+            None
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Computer pilot flying a ship from the game state, for the attract mode demo
+
+use std::f32::consts::PI;
+
+use macroquad::prelude::*;
+
+use crate::{
+    wrapped_offset, Game, ShipInput, BULLET_SIZE, BULLET_SPEED, BULLET_TTL,
+    ENERGY_SHIELD_MIN_ACTIVATION_ENERGY, SHIP_COLLISION_RADIUS, SHIP_HYPERSPACE_FREQUENCY,
+};
+
+// how far ahead the pilot looks for things about to hit it
+const PILOT_LOOKAHEAD: f32 = 1.0;
+// extra room the pilot keeps between itself and anything flying past
+const PILOT_SAFETY_MARGIN: f32 = 15.0;
+// collisions sooner than this can't be flown away from
+const PILOT_PANIC_TIME: f32 = 0.25;
+// threats this close at once leave nowhere to fly
+const PILOT_CORNERED_RADIUS: f32 = 90.0;
+const PILOT_CORNERED_THREATS: usize = 3;
+// how closely the ship has to point at its aim before firing or thrusting
+const PILOT_FIRE_ANGLE: f32 = 0.08;
+const PILOT_THRUST_ANGLE: f32 = 0.5;
+// the pilot closes in on targets further away than this
+const PILOT_ENGAGE_DISTANCE: f32 = 220.0;
+
+/// Something that can destroy the ship, relative to it
+struct Threat {
+    offset: Vec2,
+    vel: Vec2,
+    radius: f32,
+    bullet: bool,
+}
+
+impl Threat {
+    /// Time until the threat comes closest to the ship, and how close that is
+    fn closest_approach(&self, ship_vel: Vec2) -> (f32, f32) {
+        let rel_vel = self.vel - ship_vel;
+        let t = if rel_vel.length_squared() > f32::EPSILON {
+            (-self.offset.dot(rel_vel) / rel_vel.length_squared()).max(0.0)
+        } else {
+            0.0
+        };
+        (t, (self.offset + rel_vel * t).length())
+    }
+
+    fn will_hit(&self, ship_vel: Vec2) -> Option<f32> {
+        let (t, distance) = self.closest_approach(ship_vel);
+        (t < PILOT_LOOKAHEAD
+            && distance < self.radius + SHIP_COLLISION_RADIUS + PILOT_SAFETY_MARGIN)
+            .then_some(t)
+    }
+}
+
+/// Time for a bullet fired now to reach a target at the offset moving with the velocity,
+/// none if it can't catch it
+pub fn intercept_time(offset: Vec2, vel: Vec2, bullet_speed: f32) -> Option<f32> {
+    // solve |offset + vel * t| = bullet_speed * t for the earliest positive t
+    let a = vel.length_squared() - bullet_speed * bullet_speed;
+    let b = 2.0 * offset.dot(vel);
+    let c = offset.length_squared();
+    if a.abs() < f32::EPSILON {
+        return (b < 0.0).then(|| -c / b);
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+        .into_iter()
+        .filter(|t| *t > 0.0)
+        .min_by(f32::total_cmp)
+}
+
+/// Turns towards the direction, saying how far off it still is
+fn steer(input: &mut ShipInput, rot: f32, direction: Vec2) -> f32 {
+    // ships point up at zero rotation, with rotation increasing clockwise
+    let target = direction.x.atan2(-direction.y);
+    let diff = (target - rot + PI).rem_euclid(2.0 * PI) - PI;
+    input.left = diff < -PILOT_FIRE_ANGLE / 2.0;
+    input.right = diff > PILOT_FIRE_ANGLE / 2.0;
+    diff.abs()
+}
+
+/// Inputs for the player's ship: dodge what is about to hit it, otherwise hunt the nearest threat
+pub fn pilot(game: &Game, player: usize) -> ShipInput {
+    let mut input = ShipInput::default();
+    let player = &game.players[player];
+    let ship = &player.ship;
+    if ship.state.is_destroyed() || ship.state.is_translating() {
+        return input;
+    }
+
+    let threats: Vec<Threat> = game
+        .asteroids
+        .iter()
+        .map(|a| (a.pos, a.vel, a.size.size(), false))
+        .chain(
+            game.saucers
+                .iter()
+                .map(|s| (s.pos, s.vel, s.size.size(), false)),
+        )
+        .chain(
+            game.saucer_bullets
+                .iter()
+                .map(|(b, _)| (b.pos, b.vel, BULLET_SIZE, true)),
+        )
+        .map(|(pos, vel, radius, bullet)| Threat {
+            offset: wrapped_offset(ship.pos, pos, game.bounds),
+            vel,
+            radius,
+            bullet,
+        })
+        .collect();
+
+    let incoming: Vec<(f32, &Threat)> = threats
+        .iter()
+        .filter_map(|t| t.will_hit(ship.vel).map(|time| (time, t)))
+        .collect();
+    let soonest = incoming.iter().min_by(|a, b| a.0.total_cmp(&b.0));
+    let nearby = threats
+        .iter()
+        .filter(|t| t.offset.length() < PILOT_CORNERED_RADIUS + t.radius)
+        .count();
+
+    if let Some(&(time, threat)) = soonest {
+        let cornered = nearby >= PILOT_CORNERED_THREATS || time < PILOT_PANIC_TIME;
+        if cornered
+            && game.ship_ability.has_hyperspace()
+            && player.last_hyperspace > SHIP_HYPERSPACE_FREQUENCY
+        {
+            input.hyperspace = true;
+            return input;
+        }
+        // bullets are small enough to block, everything else has to be avoided
+        if threat.bullet
+            && game.ship_ability.has_shield()
+            && ship.energy_shield_energy >= ENERGY_SHIELD_MIN_ACTIVATION_ENERGY
+        {
+            input.shield = true;
+        }
+
+        // fly across the threat's path, on the side it is already going to miss
+        let path = threat.vel - ship.vel;
+        let across = Vec2::new(-path.y, path.x).normalize_or_zero();
+        let away = if across.dot(threat.offset) > 0.0 {
+            -across
+        } else {
+            across
+        };
+        if steer(&mut input, ship.rot, away) < PILOT_THRUST_ANGLE {
+            input.thrust = true;
+        }
+        return input;
+    }
+
+    // shoot at whatever is closest, leading it so the bullet arrives where it will be
+    let Some(target) = threats
+        .iter()
+        .filter(|t| !t.bullet)
+        .min_by(|a, b| a.offset.length().total_cmp(&b.offset.length()))
+    else {
+        return input;
+    };
+    let Some(time) = intercept_time(target.offset, target.vel, BULLET_SPEED) else {
+        steer(&mut input, ship.rot, target.offset);
+        return input;
+    };
+    let aim = target.offset + target.vel * time;
+    let error = steer(&mut input, ship.rot, aim);
+    input.fire = error < PILOT_FIRE_ANGLE && time < BULLET_TTL;
+    input.thrust = error < PILOT_THRUST_ANGLE && target.offset.length() > PILOT_ENGAGE_DISTANCE;
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rollback::tests::SIMULATION, Asteroid, AsteroidType, Bullet, ShipAbility};
+
+    fn game_with_ship_at(pos: Vec2) -> Game {
+        let mut game = Game::new(1, Vec2::new(800.0, 600.0));
+        game.players[0].ship.pos = pos;
+        game.players[0].last_hyperspace = SHIP_HYPERSPACE_FREQUENCY + 1.0;
+        game
+    }
+
+    #[test]
+    fn intercept_leads_a_moving_target() {
+        let offset = Vec2::new(100.0, 0.0);
+        let vel = Vec2::new(0.0, 50.0);
+        let t = intercept_time(offset, vel, BULLET_SPEED).unwrap();
+        let hit = offset + vel * t;
+        assert!((hit.length() - BULLET_SPEED * t).abs() < 0.01);
+
+        // a target running away faster than a bullet can't be caught
+        assert_eq!(
+            intercept_time(offset, Vec2::new(200.0, 0.0), BULLET_SPEED),
+            None
+        );
+    }
+
+    #[test]
+    fn turns_towards_and_fires_at_the_nearest_asteroid() {
+        // asteroids get their shape from macroquad's shared random numbers
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = game_with_ship_at(Vec2::new(400.0, 300.0));
+        game.asteroids.push(Asteroid::new(
+            AsteroidType::Large,
+            Vec2::new(400.0, 100.0),
+            Vec2::ZERO,
+        ));
+        game.asteroids.push(Asteroid::new(
+            AsteroidType::Large,
+            Vec2::new(700.0, 300.0),
+            Vec2::ZERO,
+        ));
+
+        // already pointing straight up at the nearest one
+        let input = pilot(&game, 0);
+        assert!(input.fire && !input.left && !input.right);
+
+        game.players[0].ship.rot = 1.0;
+        let input = pilot(&game, 0);
+        assert!(input.left && !input.fire);
+    }
+
+    #[test]
+    fn hyperspaces_out_of_an_unavoidable_bullet() {
+        let mut game = game_with_ship_at(Vec2::new(400.0, 300.0));
+        game.saucer_bullets.push((
+            Bullet {
+                pos: Vec2::new(400.0, 330.0),
+                vel: Vec2::new(0.0, -BULLET_SPEED),
+                collided: false,
+            },
+            0.0,
+        ));
+        assert!(pilot(&game, 0).hyperspace);
+
+        // with hyperspace recharging it blocks with the shield instead
+        game.players[0].last_hyperspace = 0.0;
+        let input = pilot(&game, 0);
+        assert!(input.shield && !input.hyperspace);
+
+        // as it does when the shield has taken the place of hyperspace
+        game.players[0].last_hyperspace = SHIP_HYPERSPACE_FREQUENCY + 1.0;
+        game.ship_ability = ShipAbility::Shield;
+        let input = pilot(&game, 0);
+        assert!(input.shield && !input.hyperspace);
+    }
+}
@@ -0,0 +1,171 @@
+//! Where each frame's time goes, shown over the debug overlay
+
+use std::collections::VecDeque;
+
+use macroquad::{miniquad::date, prelude::*};
+
+use crate::{render::Frame, Game, PROFILER_HISTORY, PROFILER_SMOOTHING};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Spawning,
+    Ships,
+    Physics,
+    Collisions,
+    Particles,
+    Trails,
+    Rendering,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 7] = [
+        Self::Spawning,
+        Self::Ships,
+        Self::Physics,
+        Self::Collisions,
+        Self::Particles,
+        Self::Trails,
+        Self::Rendering,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Spawning => "spawning",
+            Self::Ships => "ships",
+            Self::Physics => "physics",
+            Self::Collisions => "collisions",
+            Self::Particles => "particles",
+            Self::Trails => "trails",
+            Self::Rendering => "rendering",
+        }
+    }
+}
+
+/// Seconds spent in each phase, timed a lap at a time. Uses miniquad's clock as std's isn't
+/// available on the web
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PhaseTimes {
+    times: [f64; Phase::ALL.len()],
+    lap_start: f64,
+}
+
+impl PhaseTimes {
+    /// Clears the times and starts the first lap
+    pub fn start(&mut self) {
+        self.times = Default::default();
+        self.lap_start = date::now();
+    }
+
+    /// Adds the time since the last lap to the phase
+    pub fn lap(&mut self, phase: Phase) {
+        let now = date::now();
+        self.times[phase as usize] += now - self.lap_start;
+        self.lap_start = now;
+    }
+
+    pub fn get(&self, phase: Phase) -> f64 {
+        self.times[phase as usize]
+    }
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    // seconds each recent frame took, oldest first
+    frame_times: VecDeque<f32>,
+    // running averages of each phase, in seconds
+    phases: [f64; Phase::ALL.len()],
+}
+
+impl Profiler {
+    /// Takes in a frame, along with how long the game took to update and draw
+    pub fn record(&mut self, frame_time: f32, update: Option<&PhaseTimes>, rendering: f64) {
+        self.frame_times.push_back(frame_time);
+        if self.frame_times.len() > PROFILER_HISTORY {
+            self.frame_times.pop_front();
+        }
+
+        for phase in Phase::ALL {
+            let time = match (phase, update) {
+                (Phase::Rendering, _) => rendering,
+                (_, Some(update)) => update.get(phase),
+                // paused, nothing was updated
+                (_, None) => 0.0,
+            };
+            let average = &mut self.phases[phase as usize];
+            *average += (time - *average) * PROFILER_SMOOTHING;
+        }
+    }
+
+    /// Phase timings, entity counts and a graph of recent frame times down the right of the
+    /// screen
+    pub fn frame(&self, game: Option<&Game>, screen: Vec2) -> Frame {
+        let mut frame = Frame::default();
+        let (width, x) = (240.0, screen.x - 250.0);
+        let mut y = 20.0;
+        let mut line = |text: &str, color: Color| {
+            frame.text(text, Vec2::new(x, y), 16.0, color);
+            y += 16.0;
+        };
+
+        for phase in Phase::ALL {
+            let ms = self.phases[phase as usize] * 1000.0;
+            line(&format!("{:<12}{ms:6.3} ms", phase.name()), RED);
+        }
+        if let Some(game) = game {
+            let bullets: usize = game.players.iter().map(|p| p.bullets.len()).sum();
+            line(&format!("asteroids {}", game.asteroids.len()), RED);
+            line(&format!("bullets {bullets}"), RED);
+            line(
+                &format!("saucer bullets {}", game.saucer_bullets.len()),
+                RED,
+            );
+            line(&format!("particles {}", game.particles.len()), RED);
+        }
+
+        // frame times up to 50 ms against the 60 and 30 fps budgets
+        let (top, height) = (y, 60.0);
+        let scale = height / (1.0 / 20.0);
+        frame.rectangle_lines(x, top, width, height, 1.0, DARKGRAY);
+        for (budget, color) in [(1.0 / 60.0, DARKGREEN), (1.0 / 30.0, ORANGE)] {
+            let y = top + height - budget * scale;
+            frame.line(Vec2::new(x, y), Vec2::new(x + width, y), 1.0, color);
+        }
+        let step = width / PROFILER_HISTORY as f32;
+        for (i, time) in self.frame_times.iter().enumerate() {
+            let bar = (time * scale).min(height);
+            let x = x + i as f32 * step;
+            frame.line(
+                Vec2::new(x, top + height),
+                Vec2::new(x, top + height - bar),
+                step.max(1.0),
+                YELLOW,
+            );
+        }
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollback::tests::SIMULATION;
+
+    #[test]
+    fn updates_are_timed_a_phase_at_a_time() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut game = Game::new(1, Vec2::new(800.0, 600.0));
+        game.update(1.0 / 60.0, &[]);
+        let total: f64 = Phase::ALL.iter().map(|p| game.profile.get(*p)).sum();
+        assert!(total > 0.0);
+        assert_eq!(game.profile.get(Phase::Rendering), 0.0);
+
+        let mut profiler = Profiler::default();
+        for _ in 0..PROFILER_HISTORY + 10 {
+            profiler.record(0.02, Some(&game.profile), 0.001);
+        }
+        assert_eq!(profiler.frame_times.len(), PROFILER_HISTORY);
+        // the averages settle on what is being recorded
+        let rendering = profiler.phases[Phase::Rendering as usize];
+        assert!((rendering - 0.001).abs() < 1e-6);
+    }
+}
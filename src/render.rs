@@ -0,0 +1,540 @@
+//! A frame's drawing as a list of primitives, played back on the screen through macroquad or
+//! rasterised on the CPU, which lets scenes be checked against golden images without a GPU
+
+use std::path::Path;
+
+use macroquad::prelude::*;
+
+use crate::draw_centered_text;
+
+// there's no font to rasterise on the CPU so text is blocked out a character at a time, these
+// being the spacing and size of each block as a fraction of the font size
+const CANVAS_TEXT_ADVANCE: f32 = 0.5;
+const CANVAS_GLYPH_WIDTH: f32 = 0.4;
+const CANVAS_GLYPH_HEIGHT: f32 = 0.55;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Primitive {
+    Line {
+        from: Vec2,
+        to: Vec2,
+        thickness: f32,
+        color: Color,
+    },
+    Circle {
+        center: Vec2,
+        radius: f32,
+        color: Color,
+    },
+    /// A ring along the edge of the circle
+    CircleLines {
+        center: Vec2,
+        radius: f32,
+        thickness: f32,
+        color: Color,
+    },
+    /// Filled in, the points going around a convex outline
+    Polygon { points: Vec<Vec2>, color: Color },
+    /// Placed by the start of its baseline, or by its middle if centred
+    Text {
+        text: String,
+        pos: Vec2,
+        size: f32,
+        color: Color,
+        centered: bool,
+    },
+}
+
+/// Everything drawn in a frame, in the order it was drawn
+#[derive(Debug, Default)]
+pub struct Frame {
+    pub primitives: Vec<Primitive>,
+}
+
+impl Frame {
+    pub fn line(&mut self, from: Vec2, to: Vec2, thickness: f32, color: Color) {
+        self.primitives.push(Primitive::Line {
+            from,
+            to,
+            thickness,
+            color,
+        });
+    }
+
+    /// Lines joining the points in turn and the last back to the first
+    pub fn outline(&mut self, points: &[Vec2], thickness: f32, color: Color) {
+        for (from, to) in points.iter().zip(points.iter().cycle().skip(1)) {
+            self.line(*from, *to, thickness, color);
+        }
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: Color) {
+        self.primitives.push(Primitive::Circle {
+            center,
+            radius,
+            color,
+        });
+    }
+
+    pub fn circle_lines(&mut self, center: Vec2, radius: f32, thickness: f32, color: Color) {
+        self.primitives.push(Primitive::CircleLines {
+            center,
+            radius,
+            thickness,
+            color,
+        });
+    }
+
+    pub fn polygon(&mut self, points: Vec<Vec2>, color: Color) {
+        self.primitives.push(Primitive::Polygon { points, color });
+    }
+
+    pub fn rectangle(&mut self, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        let points = vec![
+            Vec2::new(x, y),
+            Vec2::new(x + w, y),
+            Vec2::new(x + w, y + h),
+            Vec2::new(x, y + h),
+        ];
+        self.polygon(points, color);
+    }
+
+    /// Lines around the inside of the rectangle's edge, as macroquad draws them
+    pub fn rectangle_lines(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        thickness: f32,
+        color: Color,
+    ) {
+        let inset = thickness / 2.0;
+        let points = [
+            Vec2::new(x + inset, y + inset),
+            Vec2::new(x + w - inset, y + inset),
+            Vec2::new(x + w - inset, y + h - inset),
+            Vec2::new(x + inset, y + h - inset),
+        ];
+        self.outline(&points, thickness, color);
+    }
+
+    pub fn text(&mut self, text: &str, pos: Vec2, size: f32, color: Color) {
+        self.primitives.push(Primitive::Text {
+            text: text.to_string(),
+            pos,
+            size,
+            color,
+            centered: false,
+        });
+    }
+
+    pub fn centered_text(&mut self, text: &str, pos: Vec2, size: f32, color: Color) {
+        self.primitives.push(Primitive::Text {
+            text: text.to_string(),
+            pos,
+            size,
+            color,
+            centered: true,
+        });
+    }
+}
+
+pub trait Renderer {
+    fn render(&mut self, frame: &Frame);
+}
+
+/// Draws through macroquad, with whichever camera and material are in use
+pub struct Screen;
+
+impl Renderer for Screen {
+    fn render(&mut self, frame: &Frame) {
+        for primitive in &frame.primitives {
+            match primitive {
+                Primitive::Line {
+                    from,
+                    to,
+                    thickness,
+                    color,
+                } => draw_line(from.x, from.y, to.x, to.y, *thickness, *color),
+                Primitive::Circle {
+                    center,
+                    radius,
+                    color,
+                } => draw_circle(center.x, center.y, *radius, *color),
+                Primitive::CircleLines {
+                    center,
+                    radius,
+                    thickness,
+                    color,
+                } => draw_circle_lines(center.x, center.y, *radius, *thickness, *color),
+                Primitive::Polygon { points, color } => {
+                    for pair in points.get(1..).unwrap_or_default().windows(2) {
+                        draw_triangle(points[0], pair[0], pair[1], *color);
+                    }
+                }
+                Primitive::Text {
+                    text,
+                    pos,
+                    size,
+                    color,
+                    centered: true,
+                } => draw_centered_text(text, pos.x, pos.y, *size, *color),
+                Primitive::Text {
+                    text,
+                    pos,
+                    size,
+                    color,
+                    centered: false,
+                } => {
+                    draw_text(text, pos.x, pos.y, *size, *color);
+                }
+            }
+        }
+    }
+}
+
+/// Rasterises frames into an image on the CPU, smoothing the edges of lines and circles
+pub struct Canvas {
+    image: Image,
+    // part of the world shown, and pixels per unit of it
+    view: Rect,
+    scale: f32,
+}
+
+impl Canvas {
+    /// A canvas filled with the background, showing the view stretched across its width
+    pub fn new(width: u16, height: u16, view: Rect, background: Color) -> Self {
+        Self {
+            image: Image::gen_image_color(width, height, background),
+            view,
+            scale: width as f32 / view.w,
+        }
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        save_png(&self.image, path)
+    }
+
+    fn to_pixels(&self, pos: Vec2) -> Vec2 {
+        (pos - self.view.point()) * self.scale
+    }
+
+    /// Blends the colour into every pixel in the box, by how much of the pixel the shape
+    /// covers at its centre
+    fn fill(&mut self, min: Vec2, max: Vec2, color: Color, coverage: impl Fn(Vec2) -> f32) {
+        let (width, height) = (self.image.width as f32, self.image.height as f32);
+        let (x0, x1) = (min.x.floor().max(0.0), max.x.ceil().min(width));
+        let (y0, y1) = (min.y.floor().max(0.0), max.y.ceil().min(height));
+        for y in y0 as u32..y1.max(y0) as u32 {
+            for x in x0 as u32..x1.max(x0) as u32 {
+                let amount = coverage(Vec2::new(x as f32 + 0.5, y as f32 + 0.5)) * color.a;
+                if amount > 0.0 {
+                    let under = self.image.get_pixel(x, y);
+                    let mix = |under: f32, over: f32| under + (over - under) * amount;
+                    let blended = Color::new(
+                        mix(under.r, color.r),
+                        mix(under.g, color.g),
+                        mix(under.b, color.b),
+                        1.0,
+                    );
+                    self.image.set_pixel(x, y, blended);
+                }
+            }
+        }
+    }
+}
+
+/// Whether the point is inside the polygon, counting the edges crossed on the way out to its
+/// left
+fn inside(points: &[Vec2], p: Vec2) -> bool {
+    let mut inside = false;
+    for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+impl Renderer for Canvas {
+    fn render(&mut self, frame: &Frame) {
+        for primitive in &frame.primitives {
+            match primitive {
+                Primitive::Line {
+                    from,
+                    to,
+                    thickness,
+                    color,
+                } => {
+                    let (from, to) = (self.to_pixels(*from), self.to_pixels(*to));
+                    let half = thickness * self.scale / 2.0;
+                    let length = from.distance(to);
+                    if length == 0.0 {
+                        continue;
+                    }
+                    let along = (to - from) / length;
+                    let pad = Vec2::splat(half + 1.0);
+                    self.fill(from.min(to) - pad, from.max(to) + pad, *color, |p| {
+                        let t = (p - from).dot(along);
+                        let across = (p - from).perp_dot(along).abs();
+                        (half + 0.5 - across).clamp(0.0, 1.0)
+                            * (t + 0.5).clamp(0.0, 1.0)
+                            * (length - t + 0.5).clamp(0.0, 1.0)
+                    });
+                }
+                Primitive::Circle {
+                    center,
+                    radius,
+                    color,
+                } => {
+                    let (center, radius) = (self.to_pixels(*center), radius * self.scale);
+                    let pad = Vec2::splat(radius + 1.0);
+                    self.fill(center - pad, center + pad, *color, |p| {
+                        (radius + 0.5 - p.distance(center)).clamp(0.0, 1.0)
+                    });
+                }
+                Primitive::CircleLines {
+                    center,
+                    radius,
+                    thickness,
+                    color,
+                } => {
+                    let (center, radius) = (self.to_pixels(*center), radius * self.scale);
+                    let half = thickness * self.scale / 2.0;
+                    let pad = Vec2::splat(radius + half + 1.0);
+                    self.fill(center - pad, center + pad, *color, |p| {
+                        (half + 0.5 - (p.distance(center) - radius).abs()).clamp(0.0, 1.0)
+                    });
+                }
+                Primitive::Polygon { points, color } => {
+                    let points: Vec<Vec2> = points.iter().map(|p| self.to_pixels(*p)).collect();
+                    let min = points.iter().fold(Vec2::MAX, |min, p| min.min(*p));
+                    let max = points.iter().fold(Vec2::MIN, |max, p| max.max(*p));
+                    self.fill(min, max, *color, |p| inside(&points, p) as u8 as f32);
+                }
+                Primitive::Text {
+                    text,
+                    pos,
+                    size,
+                    color,
+                    centered,
+                } => {
+                    let size = size * self.scale;
+                    let mut start = self.to_pixels(*pos);
+                    if *centered {
+                        let width = text.chars().count() as f32 * CANVAS_TEXT_ADVANCE * size;
+                        start += Vec2::new(-width, CANVAS_GLYPH_HEIGHT * size) / 2.0;
+                    }
+                    for (i, c) in text.chars().enumerate() {
+                        if c.is_whitespace() {
+                            continue;
+                        }
+                        let min = start
+                            + Vec2::new(i as f32 * CANVAS_TEXT_ADVANCE, -CANVAS_GLYPH_HEIGHT)
+                                * size;
+                        let max = min + Vec2::new(CANVAS_GLYPH_WIDTH, CANVAS_GLYPH_HEIGHT) * size;
+                        self.fill(min, max, *color, |p| {
+                            (p.cmpge(min) & p.cmplt(max)).all() as u8 as f32
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn save_png(image: &Image, path: impl AsRef<Path>) -> image::ImageResult<()> {
+    image::save_buffer(
+        path,
+        &image.bytes,
+        image.width as u32,
+        image.height as u32,
+        image::ColorType::Rgba8,
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        achievements::Achievements, events::GameEvent, game_over_frame, profiler::Profiler,
+        rollback::tests::SIMULATION, settings::Settings, simulate::SIM_PLAYFIELD, title_frame,
+        Asteroid, AsteroidType, Bullet, Game, Kill, Saucer, SaucerSize, Session, TimeControl, View,
+        BACKGROUND_COLOR, PROFILER_HISTORY,
+    };
+    use macroquad::rand;
+
+    const GOLDEN_IMAGES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    /// Compares the image with the golden image of that name, or saves it as the golden
+    /// image when UPDATE_GOLDEN is set
+    pub fn assert_golden(rendered: &Image, name: &str) {
+        let path = Path::new(GOLDEN_IMAGES).join(format!("{name}.png"));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(GOLDEN_IMAGES).unwrap();
+            save_png(rendered, &path).unwrap();
+            return;
+        }
+
+        let golden = image::open(&path)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "can't open {}, UPDATE_GOLDEN=1 makes it: {e}",
+                    path.display()
+                )
+            })
+            .to_rgba8();
+        assert_eq!(
+            golden.dimensions(),
+            (rendered.width as u32, rendered.height as u32)
+        );
+        // a little leeway for floating point coming out differently on other platforms
+        let differing = golden
+            .as_raw()
+            .iter()
+            .zip(&rendered.bytes)
+            .filter(|(a, b)| a.abs_diff(**b) > 2)
+            .count();
+        if differing > 0 {
+            let actual = std::env::temp_dir().join(format!("{name}.actual.png"));
+            save_png(rendered, &actual).unwrap();
+            panic!(
+                "{differing} bytes differ from {}, the render is at {}",
+                path.display(),
+                actual.display()
+            );
+        }
+    }
+
+    #[test]
+    fn primitives_cover_their_pixels() {
+        let mut frame = Frame::default();
+        frame.line(Vec2::new(4.0, 5.0), Vec2::new(36.0, 5.0), 2.0, WHITE);
+        frame.circle(Vec2::new(11.0, 21.0), 4.0, WHITE);
+        frame.rectangle(24.0, 16.0, 8.0, 8.0, Color::new(0.0, 0.0, 1.0, 0.5));
+        frame.text("ab", Vec2::new(4.0, 40.0), 20.0, WHITE);
+        // drawn at half size
+        let mut canvas = Canvas::new(20, 20, Rect::new(0.0, 0.0, 40.0, 40.0), BLACK);
+        canvas.render(&frame);
+        let image = canvas.image();
+
+        assert_eq!(image.get_pixel(10, 2), WHITE);
+        assert_eq!(image.get_pixel(10, 3), BLACK);
+        assert_eq!(image.get_pixel(5, 10), WHITE);
+        // pixels on the edge are as bright as how much of them is covered
+        assert!((image.get_pixel(3, 10).r - 0.5).abs() < 0.01);
+        assert!((image.get_pixel(14, 10).b - 0.5).abs() < 0.01);
+        // a block for each letter with a gap between them
+        assert_eq!(image.get_pixel(3, 19), WHITE);
+        assert_eq!(image.get_pixel(6, 19), BLACK);
+        assert_eq!(image.get_pixel(8, 19), WHITE);
+        assert_eq!(image.get_pixel(3, 13), BLACK);
+    }
+
+    /// A playfield with one of most things on it, drawn at half size. Asteroid shapes come from
+    /// macroquad's shared random numbers, so the simulation lock has to be held
+    pub fn golden_scene() -> Canvas {
+        rand::srand(3);
+        let mut game = Game::new(1, SIM_PLAYFIELD);
+        for (size, pos) in [
+            (AsteroidType::Large, Vec2::new(150.0, 150.0)),
+            (AsteroidType::Medium, Vec2::new(600.0, 120.0)),
+            (AsteroidType::Small, Vec2::new(650.0, 450.0)),
+        ] {
+            game.asteroids.push(Asteroid::new(size, pos, Vec2::ZERO));
+        }
+        let mut saucer = Saucer::new(SaucerSize::Large, game.bounds);
+        saucer.pos = Vec2::new(250.0, 450.0);
+        game.saucers.push(saucer);
+        let player = &mut game.players[0];
+        player.ship.rot = 0.5;
+        player.score = 1200;
+        let bullet = Bullet {
+            pos: Vec2::new(460.0, 250.0),
+            vel: Vec2::ZERO,
+            collided: false,
+        };
+        player.bullets.push((bullet, 0.0));
+        game.add_score_text("200".to_string(), Vec2::new(150.0, 90.0), WHITE);
+
+        let view = View::new(&Settings {
+            star_density: 0.0,
+            ..Default::default()
+        });
+        let mut canvas = Canvas::new(
+            400,
+            300,
+            Rect::new(0.0, 0.0, 800.0, 600.0),
+            BACKGROUND_COLOR,
+        );
+        canvas.render(&game.frame(&view));
+        canvas
+    }
+
+    #[test]
+    fn playfield_matches_golden_image() {
+        let _lock = SIMULATION.lock().unwrap();
+        assert_golden(golden_scene().image(), "playfield");
+    }
+
+    #[test]
+    fn screens_match_golden_images() {
+        let _lock = SIMULATION.lock().unwrap();
+        let screen = |frame: &Frame| {
+            let mut canvas = Canvas::new(
+                400,
+                300,
+                Rect::new(0.0, 0.0, 800.0, 600.0),
+                BACKGROUND_COLOR,
+            );
+            canvas.render(frame);
+            canvas
+        };
+
+        assert_golden(
+            screen(&title_frame(&Settings::default(), SIM_PLAYFIELD, true)).image(),
+            "title",
+        );
+
+        let mut game = Game::new_deathmatch(2, SIM_PLAYFIELD);
+        game.players[1].kills = 4;
+        game.players[1].score = 4000;
+        let results = Session::Simultaneous(game);
+        assert_golden(
+            screen(&game_over_frame(&results, SIM_PLAYFIELD)).image(),
+            "match_results",
+        );
+
+        // part way through, with one just unlocked
+        let mut achievements = Achievements::parse("century 40\nsurvivor 10\n");
+        achievements.handle(&[GameEvent::SaucerDestroyed {
+            size: SaucerSize::Small,
+            by: Some(0),
+            kill: Some(Kill::Shot { shots: 1 }),
+            pos: Vec2::ZERO,
+        }]);
+        achievements.update_toasts(0.5);
+        let mut frame = achievements.screen_frame(SIM_PLAYFIELD);
+        frame
+            .primitives
+            .extend(achievements.toast_frame(SIM_PLAYFIELD).primitives);
+        assert_golden(screen(&frame).image(), "achievements");
+
+        let mut profiler = Profiler::default();
+        for i in 0..PROFILER_HISTORY {
+            profiler.record(0.01 + (i % 20) as f32 * 0.001, None, 0.004);
+        }
+        let mut time = TimeControl::default();
+        time.paused = true;
+        let mut frame = profiler.frame(None, SIM_PLAYFIELD);
+        frame
+            .primitives
+            .extend(time.frame(SIM_PLAYFIELD).primitives);
+        assert_golden(screen(&frame).image(), "overlays");
+    }
+}
@@ -0,0 +1,353 @@
+//! Rollback simulation: missing inputs are predicted so the game never waits on the network, and
+//! once the real ones arrive any mispredicted ticks are re-simulated from a snapshot
+
+use std::collections::{BTreeMap, VecDeque};
+
+use macroquad::{prelude::*, rand};
+
+use crate::{
+    events::GameEvent, Game, ShipInput, NET_CHECKSUM_INTERVAL, NET_MAX_ROLLBACK, NET_TICK,
+};
+
+/// Hash of everything in the game that affects how it plays out, equal on peers that are in sync
+pub fn checksum(game: &Game) -> u64 {
+    // FNV-1a, so the value doesn't depend on the standard library's hasher
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut write = |value: u64| {
+        for byte in value.to_le_bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    let mut write_vec = |v: Vec2| write((v.x.to_bits() as u64) << 32 | v.y.to_bits() as u64);
+
+    write_vec(game.bounds);
+    for player in &game.players {
+        write_vec(player.ship.pos);
+        write_vec(player.ship.vel);
+        write_vec(Vec2::new(player.ship.rot, player.ship.energy_shield_energy));
+        write_vec(Vec2::new(player.last_bullet, player.last_hyperspace));
+        player.bullets.iter().for_each(|(b, _)| write_vec(b.pos));
+        write_vec(Vec2::new(
+            player.score as f32,
+            player.lives.remaining() as f32,
+        ));
+        write_vec(Vec2::new(player.kills as f32, player.combo as f32));
+    }
+    game.asteroids.iter().for_each(|a| {
+        write_vec(a.pos);
+        write_vec(a.vel);
+    });
+    game.saucers.iter().for_each(|s| write_vec(s.pos));
+    game.saucer_bullets
+        .iter()
+        .for_each(|(b, _)| write_vec(b.pos));
+    write_vec(Vec2::new(game.asteroid_wave as f32, game.frame as f32));
+    write_vec(Vec2::new(game.last_asteroid, game.last_saucer));
+
+    hash
+}
+
+pub struct Rollback {
+    game: Game,
+    seed: u64,
+    // next tick to simulate
+    tick: u32,
+    // first tick still missing someone's input, everything before it is final
+    confirmed_tick: u32,
+    // real inputs as they arrive, for the ticks that aren't final yet
+    inputs: BTreeMap<u32, Vec<Option<ShipInput>>>,
+    // each player's most recent real input and its tick, used to predict the ones missing
+    last_inputs: Vec<(u32, ShipInput)>,
+    // the game before each tick that may still be re-simulated, the inputs it was run with and
+    // what happened during it
+    history: VecDeque<(u32, Game, Vec<ShipInput>, Vec<GameEvent>)>,
+    // earliest simulated tick found to have been run with the wrong inputs
+    rollback_to: Option<u32>,
+    // checksums of final states not yet collected
+    checksums: Vec<(u32, u64)>,
+    // events of final ticks not yet collected
+    events: Vec<GameEvent>,
+}
+
+impl Rollback {
+    pub fn new(game: Game, seed: u64) -> Self {
+        let players = game.players.len();
+        Self {
+            game,
+            seed,
+            tick: 0,
+            confirmed_tick: 0,
+            inputs: BTreeMap::new(),
+            last_inputs: vec![(0, ShipInput::default()); players],
+            history: VecDeque::new(),
+            rollback_to: None,
+            checksums: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Whether the game has ended in a state that can no longer be rolled back
+    pub fn is_over(&self) -> bool {
+        self.game.game_over && self.history.is_empty()
+    }
+
+    /// First tick the player's input hasn't arrived for
+    pub fn next_missing(&self, player: usize) -> u32 {
+        let mut tick = self.confirmed_tick;
+        while self
+            .inputs
+            .get(&tick)
+            .is_some_and(|inputs| inputs[player].is_some())
+        {
+            tick += 1;
+        }
+        tick
+    }
+
+    /// Records a player's real input, scheduling a rollback if the tick was run with a different one
+    pub fn add_input(&mut self, player: usize, tick: u32, input: ShipInput) {
+        if tick < self.confirmed_tick {
+            return;
+        }
+        let players = self.game.players.len();
+        let slot = &mut self
+            .inputs
+            .entry(tick)
+            .or_insert_with(|| vec![None; players])[player];
+        // inputs are sent more than once in case some get lost
+        if slot.is_some() {
+            return;
+        }
+        *slot = Some(input);
+
+        if tick >= self.last_inputs[player].0 {
+            self.last_inputs[player] = (tick, input);
+        }
+
+        if tick < self.tick {
+            let (first, _, _, _) = &self.history[0];
+            let (_, _, used, _) = &self.history[(tick - first) as usize];
+            if used[player] != input {
+                self.rollback_to = Some(self.rollback_to.map_or(tick, |t| t.min(tick)));
+            }
+        }
+
+        while self
+            .inputs
+            .get(&self.confirmed_tick)
+            .is_some_and(|inputs| inputs.iter().all(Option::is_some))
+        {
+            self.confirmed_tick += 1;
+        }
+        self.prune();
+    }
+
+    /// Whether the next tick can be simulated without going further ahead than can be rolled back
+    pub fn can_advance(&self) -> bool {
+        self.tick < self.confirmed_tick + NET_MAX_ROLLBACK
+    }
+
+    /// Simulates the next tick, predicting any inputs that haven't arrived
+    pub fn advance(&mut self) {
+        self.resimulate();
+
+        let inputs: Vec<ShipInput> = (0..self.game.players.len())
+            .map(|player| {
+                self.inputs
+                    .get(&self.tick)
+                    .and_then(|inputs| inputs[player])
+                    .unwrap_or(ShipInput {
+                        // a repeated hyperspace press would be a new jump
+                        hyperspace: false,
+                        ..self.last_inputs[player].1
+                    })
+            })
+            .collect();
+        let before = self.game.clone();
+
+        // reseed every tick so the peers' random numbers can't drift apart
+        rand::srand(self.seed ^ self.tick as u64);
+        self.game.update(NET_TICK, &inputs);
+        // held back until the tick is final, a mispredicted run's events never happened
+        let events = self.game.take_events();
+        self.history.push_back((self.tick, before, inputs, events));
+        self.tick += 1;
+        self.prune();
+    }
+
+    /// Restores the game from before the earliest mispredicted tick and runs it forward again
+    pub fn resimulate(&mut self) {
+        let Some(from) = self.rollback_to.take() else {
+            return;
+        };
+        let to = self.tick;
+        let index = (from - self.history[0].0) as usize;
+        self.game = self.history[index].1.clone();
+        self.history.truncate(index);
+        self.tick = from;
+        while self.tick < to {
+            self.advance();
+        }
+    }
+
+    /// Checksums of states that can no longer change, by the tick they start
+    pub fn take_checksums(&mut self) -> Vec<(u32, u64)> {
+        std::mem::take(&mut self.checksums)
+    }
+
+    /// Events from ticks that have become final since the last call
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Drops what can no longer be rolled back to, checksumming it and reporting its events on the
+    /// way out
+    fn prune(&mut self) {
+        let settled = self
+            .confirmed_tick
+            .min(self.tick)
+            .min(self.rollback_to.unwrap_or(u32::MAX));
+        while self
+            .history
+            .front()
+            .is_some_and(|(t, _, _, _)| *t < settled)
+        {
+            let (tick, game, _, events) = self.history.pop_front().unwrap();
+            if tick.is_multiple_of(NET_CHECKSUM_INTERVAL) {
+                self.checksums.push((tick, checksum(&game)));
+            }
+            self.events.extend(events);
+        }
+        self.inputs.retain(|t, _| *t >= settled);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    // macroquad's random numbers are global, so simulations in tests can't run side by side
+    pub static SIMULATION: Mutex<()> = Mutex::new(());
+
+    /// Inputs that change every so often, from a seed that isn't macroquad's
+    pub fn scripted_input(seed: u64, tick: u32) -> ShipInput {
+        let mut x = seed ^ (tick / 10) as u64 ^ 0x9e37_79b9_7f4a_7c15;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        ShipInput::from_bits(x as u8 & 0b10_1111)
+    }
+
+    fn new_game() -> Game {
+        rand::srand(1);
+        Game::new_deathmatch(2, Vec2::new(800.0, 600.0))
+    }
+
+    #[test]
+    fn late_inputs_end_up_where_timely_ones_do() {
+        let _lock = SIMULATION.lock().unwrap();
+        let ticks = 300;
+
+        let mut timely = Rollback::new(new_game(), 7);
+        for tick in 0..ticks {
+            timely.add_input(0, tick, scripted_input(1, tick));
+            timely.add_input(1, tick, scripted_input(2, tick));
+            timely.advance();
+        }
+
+        // the second player's inputs arrive in bursts, as late as can be rolled back
+        let mut late = Rollback::new(new_game(), 7);
+        for tick in 0..ticks {
+            late.add_input(0, tick, scripted_input(1, tick));
+            if tick % NET_MAX_ROLLBACK == NET_MAX_ROLLBACK - 1 {
+                for t in tick + 1 - NET_MAX_ROLLBACK..=tick {
+                    late.add_input(1, t, scripted_input(2, t));
+                }
+            }
+            assert!(late.can_advance());
+            late.advance();
+        }
+        late.resimulate();
+
+        assert_eq!(checksum(late.game()), checksum(timely.game()));
+        assert_eq!(late.take_checksums(), timely.take_checksums());
+    }
+
+    #[test]
+    fn reports_what_happened_once_inputs_arrive() {
+        let _lock = SIMULATION.lock().unwrap();
+        let fire = ShipInput {
+            fire: true,
+            ..Default::default()
+        };
+        let fired = |events: &[GameEvent]| {
+            events
+                .iter()
+                .filter(|e| matches!(e, GameEvent::BulletFired { player: 1, .. }))
+                .count()
+        };
+
+        // the second player starts shooting once the gun has loaded
+        let input = |tick| {
+            if tick < NET_MAX_ROLLBACK {
+                ShipInput::default()
+            } else {
+                fire
+            }
+        };
+        let ticks = NET_MAX_ROLLBACK * 2;
+
+        let mut timely = Rollback::new(new_game(), 7);
+        for tick in 0..ticks {
+            timely.add_input(0, tick, ShipInput::default());
+            timely.add_input(1, tick, input(tick));
+            timely.advance();
+        }
+        let expected = timely.take_events();
+        assert!(fired(&expected) > 0);
+
+        // predicted not to, which turns out wrong
+        let mut late = Rollback::new(new_game(), 7);
+        for tick in 0..ticks {
+            late.add_input(0, tick, ShipInput::default());
+            if tick < NET_MAX_ROLLBACK {
+                late.add_input(1, tick, input(tick));
+            }
+            late.advance();
+        }
+        let mut reported = late.take_events();
+        assert_eq!(fired(&reported), 0);
+        for tick in NET_MAX_ROLLBACK..ticks {
+            late.add_input(1, tick, input(tick));
+        }
+        late.resimulate();
+        reported.extend(late.take_events());
+        assert_eq!(reported, expected);
+    }
+
+    #[test]
+    fn stops_advancing_once_too_far_ahead() {
+        let _lock = SIMULATION.lock().unwrap();
+        let mut rollback = Rollback::new(new_game(), 7);
+        for tick in 0..NET_MAX_ROLLBACK {
+            rollback.add_input(0, tick, ShipInput::default());
+            assert!(rollback.can_advance());
+            rollback.advance();
+        }
+        assert!(!rollback.can_advance());
+
+        rollback.add_input(1, 0, ShipInput::default());
+        assert!(rollback.can_advance());
+        assert_eq!(rollback.next_missing(1), 1);
+    }
+}
@@ -0,0 +1,450 @@
+//! Player preferences, kept between runs
+
+use macroquad::{
+    hash,
+    prelude::*,
+    ui::{root_ui, widgets},
+};
+
+use crate::{
+    palette::Palette,
+    render::{Frame, Renderer, Screen},
+    storage, Controls, Tuning, BACKGROUND_COLOR, COOP_FRIENDLY_FIRE, COOP_SHARED_LIVES, DEBUG,
+    MASTER_VOLUME, MAX_PLAYERS, MUSIC_VOLUME, PLAYER_CONTROLS, SCREEN_SHAKE, SFX_VOLUME,
+    STAR_MAX_DENSITY,
+};
+
+const STORAGE_KEY: &str = "settings";
+
+/// Keys that can be bound to a control, anything else is left for the menus
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::KpEnter,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::LeftShift,
+    KeyCode::RightShift,
+    KeyCode::LeftControl,
+    KeyCode::RightControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightAlt,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::LeftBracket,
+    KeyCode::RightBracket,
+    KeyCode::Minus,
+    KeyCode::Equal,
+];
+
+/// Keys are saved under the names macroquad gives them
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .copied()
+        .find(|key| format!("{key:?}") == name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Self::Easy, Self::Normal, Self::Hard];
+
+    /// Name it is saved under
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Normal => "normal",
+            Self::Hard => "hard",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Easy => "Easy",
+            Self::Normal => "Normal",
+            Self::Hard => "Hard",
+        }
+    }
+
+    /// How local games play, saucers turn up less often and asteroids speed up more slowly on
+    /// easy, the other way round on hard
+    pub fn tuning(&self) -> Tuning {
+        let normal = Tuning::default();
+        let (saucer_spawn_rate, asteroid_spawn_decrease_factor) = match self {
+            Self::Easy => (1.5, 0.5),
+            Self::Normal => (1.0, 1.0),
+            Self::Hard => (0.6, 2.0),
+        };
+        Tuning {
+            saucer_spawn_rate: normal.saucer_spawn_rate * saucer_spawn_rate,
+            asteroid_spawn_decrease_factor: normal.asteroid_spawn_decrease_factor
+                * asteroid_spawn_decrease_factor,
+            ..normal
+        }
+    }
+}
+
+impl Palette {
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Classic => "Classic",
+            Self::Green => "Green phosphor",
+            Self::Amber => "Amber phosphor",
+            Self::White => "White phosphor",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub controls: [Controls; MAX_PLAYERS],
+    pub difficulty: Difficulty,
+    /// Players in a local co-op game draw on the first player's lives
+    pub shared_lives: bool,
+    /// Players in a local co-op game can shoot each other down
+    pub friendly_fire: bool,
+    /// How hard the screen shakes, none at zero
+    pub screen_shake: f32,
+    /// Freeze play for a moment when a saucer is shot down
+    pub hit_stop: bool,
+    /// Zoom the view in and out on an extra life
+    pub zoom_pulse: bool,
+    pub palette: Palette,
+    /// Stars in the background as a multiple of STAR_NUM
+    pub star_density: f32,
+    /// Draw through the vector monitor glow, the flat renderer otherwise
+    pub glow: bool,
+    pub debug_overlay: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: MASTER_VOLUME,
+            sfx_volume: SFX_VOLUME,
+            music_volume: MUSIC_VOLUME,
+            controls: PLAYER_CONTROLS,
+            difficulty: Difficulty::Normal,
+            shared_lives: COOP_SHARED_LIVES,
+            friendly_fire: COOP_FRIENDLY_FIRE,
+            screen_shake: SCREEN_SHAKE,
+            hit_stop: true,
+            zoom_pulse: true,
+            palette: Palette::Classic,
+            star_density: 1.0,
+            glow: false,
+            debug_overlay: DEBUG,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads back what has been saved, anything missing or unrecognised keeps its default
+    pub fn parse(saved: &str) -> Self {
+        let mut settings = Self::default();
+        for line in saved.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            let value = value.trim();
+            let number = value.parse::<f32>().ok();
+            match (key, number) {
+                ("master_volume", Some(v)) => settings.master_volume = v.clamp(0.0, 1.0),
+                ("sfx_volume", Some(v)) => settings.sfx_volume = v.clamp(0.0, 1.0),
+                ("music_volume", Some(v)) => settings.music_volume = v.clamp(0.0, 1.0),
+                ("screen_shake", Some(v)) => settings.screen_shake = v.clamp(0.0, 1.0),
+                ("star_density", Some(v)) => settings.star_density = v.clamp(0.0, STAR_MAX_DENSITY),
+                ("difficulty", _) => {
+                    if let Some(d) = Difficulty::ALL.into_iter().find(|d| d.key() == value) {
+                        settings.difficulty = d;
+                    }
+                }
+                ("palette", _) => {
+                    if let Some(p) = Palette::ALL.into_iter().find(|p| p.name() == value) {
+                        settings.palette = p;
+                    }
+                }
+                ("shared_lives", _) => {
+                    if let Ok(on) = value.parse() {
+                        settings.shared_lives = on;
+                    }
+                }
+                ("friendly_fire", _) => {
+                    if let Ok(on) = value.parse() {
+                        settings.friendly_fire = on;
+                    }
+                }
+                ("hit_stop", _) => {
+                    if let Ok(on) = value.parse() {
+                        settings.hit_stop = on;
+                    }
+                }
+                ("zoom_pulse", _) => {
+                    if let Ok(on) = value.parse() {
+                        settings.zoom_pulse = on;
+                    }
+                }
+                ("glow", _) => {
+                    if let Ok(on) = value.parse() {
+                        settings.glow = on;
+                    }
+                }
+                ("debug_overlay", _) => {
+                    if let Ok(on) = value.parse() {
+                        settings.debug_overlay = on;
+                    }
+                }
+                // key bindings are saved as p1_left and so on
+                _ => {
+                    let binding = key
+                        .strip_prefix('p')
+                        .and_then(|key| key.split_once('_'))
+                        .and_then(|(player, action)| {
+                            let player = player.parse::<usize>().ok()?.checked_sub(1)?;
+                            let action = Controls::ACTIONS.iter().position(|a| *a == action)?;
+                            Some((player, action, key_from_name(value)?))
+                        });
+                    if let Some((player, action, key)) = binding {
+                        if let Some(controls) = settings.controls.get_mut(player) {
+                            *controls.key_mut(action) = key;
+                        }
+                    }
+                }
+            }
+        }
+        settings
+    }
+
+    fn serialise(&self) -> String {
+        let mut saved = format!(
+            "master_volume {}\nsfx_volume {}\nmusic_volume {}\ndifficulty {}\nshared_lives {}\n\
+             friendly_fire {}\nscreen_shake {}\nhit_stop {}\nzoom_pulse {}\npalette {}\n\
+             star_density {}\nglow {}\ndebug_overlay {}\n",
+            self.master_volume,
+            self.sfx_volume,
+            self.music_volume,
+            self.difficulty.key(),
+            self.shared_lives,
+            self.friendly_fire,
+            self.screen_shake,
+            self.hit_stop,
+            self.zoom_pulse,
+            self.palette.name(),
+            self.star_density,
+            self.glow,
+            self.debug_overlay,
+        );
+        for (i, controls) in self.controls.iter().enumerate() {
+            for (action, key) in Controls::ACTIONS.iter().zip(controls.keys()) {
+                saved += &format!("p{}_{action} {key:?}\n", i + 1);
+            }
+        }
+        saved
+    }
+
+    pub fn load() -> Self {
+        Self::parse(&storage::load(STORAGE_KEY).unwrap_or_default())
+    }
+
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, &self.serialise());
+    }
+
+    /// Lets the player change the settings, saying whether they have finished. Rebinding is the
+    /// player and control waiting for a key to be pressed, Escape leaves it as it was
+    pub fn draw_screen(&mut self, rebinding: &mut Option<(usize, usize)>) -> bool {
+        let mut frame = Frame::default();
+        frame.rectangle(0.0, 0.0, screen_width(), screen_height(), BACKGROUND_COLOR);
+        frame.centered_text(
+            "Settings",
+            Vec2::new(screen_width() / 2.0, 60.0),
+            48.0,
+            WHITE,
+        );
+        Screen.render(&frame);
+
+        if let Some((player, action)) = *rebinding {
+            match get_last_key_pressed() {
+                Some(KeyCode::Escape) => *rebinding = None,
+                Some(key) if BINDABLE_KEYS.contains(&key) => {
+                    *self.controls[player].key_mut(action) = key;
+                    *rebinding = None;
+                }
+                _ => (),
+            }
+        }
+
+        let size = Vec2::new(420.0, (screen_height() - 170.0).max(200.0));
+        widgets::Window::new(
+            hash!(),
+            Vec2::new(screen_width() / 2.0 - size.x / 2.0, 100.0),
+            size,
+        )
+        .label("Settings")
+        .titlebar(false)
+        .movable(false)
+        .ui(&mut root_ui(), |ui| {
+            ui.label(None, "Volume");
+            ui.slider(hash!(), "Master", 0.0..1.0, &mut self.master_volume);
+            ui.slider(hash!(), "Effects", 0.0..1.0, &mut self.sfx_volume);
+            ui.slider(hash!(), "Music", 0.0..1.0, &mut self.music_volume);
+            ui.separator();
+
+            ui.label(None, "Game");
+            let titles = Difficulty::ALL.map(|d| d.title());
+            let mut difficulty = Difficulty::ALL
+                .iter()
+                .position(|d| *d == self.difficulty)
+                .unwrap_or_default();
+            ui.combo_box(hash!(), "Difficulty", &titles, &mut difficulty);
+            self.difficulty = Difficulty::ALL[difficulty];
+            ui.checkbox(hash!(), "Co-op shared lives", &mut self.shared_lives);
+            ui.checkbox(hash!(), "Co-op friendly fire", &mut self.friendly_fire);
+            ui.separator();
+
+            ui.label(None, "Display");
+            ui.slider(hash!(), "Screen shake", 0.0..1.0, &mut self.screen_shake);
+            ui.checkbox(hash!(), "Hit-stop", &mut self.hit_stop);
+            ui.checkbox(hash!(), "Zoom pulse", &mut self.zoom_pulse);
+            let titles = Palette::ALL.map(|p| p.title());
+            let mut palette = Palette::ALL
+                .iter()
+                .position(|p| *p == self.palette)
+                .unwrap_or_default();
+            ui.combo_box(hash!(), "Palette", &titles, &mut palette);
+            self.palette = Palette::ALL[palette];
+            ui.slider(
+                hash!(),
+                "Stars",
+                0.0..STAR_MAX_DENSITY,
+                &mut self.star_density,
+            );
+            ui.checkbox(hash!(), "Vector glow", &mut self.glow);
+            ui.checkbox(hash!(), "Debug overlay", &mut self.debug_overlay);
+            ui.separator();
+
+            ui.label(None, "Controls");
+            for (player, controls) in self.controls.iter().enumerate() {
+                for (action, (name, key)) in
+                    Controls::ACTIONS.iter().zip(controls.keys()).enumerate()
+                {
+                    let label = if *rebinding == Some((player, action)) {
+                        format!("P{} {name}: press a key", player + 1)
+                    } else {
+                        format!("P{} {name}: {key:?}", player + 1)
+                    };
+                    if ui.button(None, label.as_str()) {
+                        *rebinding = Some((player, action));
+                    }
+                }
+            }
+            if ui.button(None, "Reset controls") {
+                self.controls = PLAYER_CONTROLS;
+                *rebinding = None;
+            }
+        });
+
+        root_ui().button(
+            Vec2::new(screen_width() / 2.0 - 20.0, screen_height() - 50.0),
+            "Back",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_survive_a_save_and_ignore_junk() {
+        let mut settings = Settings {
+            master_volume: 0.25,
+            sfx_volume: 1.0,
+            music_volume: 0.0,
+            difficulty: Difficulty::Hard,
+            shared_lives: true,
+            friendly_fire: true,
+            screen_shake: 0.0,
+            hit_stop: false,
+            palette: Palette::Amber,
+            star_density: 1.5,
+            glow: true,
+            debug_overlay: true,
+            ..Default::default()
+        };
+        *settings.controls[1].key_mut(3) = KeyCode::Kp0;
+        assert_eq!(Settings::parse(&settings.serialise()), settings);
+
+        let parsed = Settings::parse(
+            "music_volume 7\nsfx_volume loud\nunknown 1\ndifficulty brutal\np3_left A\n\
+             p1_fire Escape\np1_thrust Up\n",
+        );
+        assert_eq!(parsed.music_volume, 1.0);
+        assert_eq!(parsed.sfx_volume, SFX_VOLUME);
+        assert_eq!(parsed.difficulty, Difficulty::Normal);
+        assert_eq!(parsed.controls[0].fire, PLAYER_CONTROLS[0].fire);
+        assert_eq!(parsed.controls[0].thrust, KeyCode::Up);
+    }
+}
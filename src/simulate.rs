@@ -0,0 +1,172 @@
+//! Headless games flown by the computer pilot, for seeing how tuning changes play out
+
+use macroquad::{prelude::*, rand};
+
+use crate::{events::GameEvent, pilot, AsteroidType, DeathCause, Game, Tuning};
+
+pub const SIM_TICK: f32 = 1.0 / 60.0;
+pub const SIM_PLAYFIELD: Vec2 = Vec2::new(800.0, 600.0);
+
+// everything that can destroy a ship in a single player game, in the order they are reported
+const SIM_DEATH_CAUSES: [DeathCause; 6] = [
+    DeathCause::Asteroid(AsteroidType::Large),
+    DeathCause::Asteroid(AsteroidType::Medium),
+    DeathCause::Asteroid(AsteroidType::Small),
+    DeathCause::Saucer,
+    DeathCause::SaucerBullet,
+    DeathCause::Hyperspace,
+];
+
+/// How a single game went
+pub struct GameStats {
+    pub seed: u64,
+    /// Seconds until the ship was hit for the last time, or the time limit if it still had lives
+    /// left
+    pub survival_time: f32,
+    /// Wave the game ended on, counting from one
+    pub wave: usize,
+    pub score: usize,
+    /// Whether every life was lost before the time limit
+    pub game_over: bool,
+    /// What destroyed the ship each time, oldest first
+    pub deaths: Vec<&'static str>,
+}
+
+/// Plays a single player game with the pilot until it is over or the time limit is reached
+pub fn simulate(tuning: Tuning, seed: u64, max_time: f32) -> GameStats {
+    rand::srand(seed);
+    let mut game = Game {
+        tuning,
+        ..Game::new(1, SIM_PLAYFIELD)
+    };
+    let mut ticks = 0u64;
+    let mut deaths = Vec::new();
+    // when the ship was last hit, the game only ending once its explosion has finished
+    let mut last_hit = 0u64;
+    while !game.game_over && (ticks as f32) * SIM_TICK < max_time {
+        // reseeding each tick keeps games reproducible whatever else uses the generator
+        rand::srand(seed ^ (ticks << 16));
+        let input = pilot(&game, 0);
+        game.update(SIM_TICK, &[input]);
+        ticks += 1;
+        for event in game.take_events() {
+            if let GameEvent::ShipHit { cause, .. } = event {
+                deaths.push(cause.name());
+                last_hit = ticks;
+            }
+        }
+    }
+    let survived = if game.game_over { last_hit } else { ticks };
+
+    let player = &game.players[0];
+    GameStats {
+        seed,
+        survival_time: survived as f32 * SIM_TICK,
+        wave: game.asteroid_wave + 1,
+        score: player.score,
+        game_over: game.game_over,
+        deaths,
+    }
+}
+
+/// Statistics over a batch of games played with the same tuning
+pub struct Summary {
+    pub tuning: Tuning,
+    pub games: usize,
+    pub mean_survival_time: f32,
+    pub median_survival_time: f32,
+    pub mean_wave: f32,
+    pub max_wave: usize,
+    pub mean_score: f32,
+    /// Minimum, lower quartile, median, upper quartile and maximum
+    pub score_quartiles: [usize; 5],
+    /// How many ships each cause destroyed across all the games
+    pub deaths: Vec<(&'static str, usize)>,
+    /// How many games each cause ended, the rest ran out of time
+    pub final_deaths: Vec<(&'static str, usize)>,
+}
+
+impl Summary {
+    pub fn new(tuning: Tuning, stats: &[GameStats]) -> Self {
+        let games = stats.len();
+        let mean = |values: Vec<f32>| values.iter().sum::<f32>() / games.max(1) as f32;
+
+        let mut survival: Vec<f32> = stats.iter().map(|s| s.survival_time).collect();
+        survival.sort_by(f32::total_cmp);
+        let mut scores: Vec<usize> = stats.iter().map(|s| s.score).collect();
+        scores.sort();
+        // nearest rank, so every value is a score that was actually reached
+        let quantile = |q: f32| {
+            let rank = ((scores.len().max(1) - 1) as f32 * q).round() as usize;
+            scores.get(rank).copied().unwrap_or(0)
+        };
+
+        let count = |deaths: Vec<&str>| {
+            SIM_DEATH_CAUSES
+                .iter()
+                .map(|cause| {
+                    let name = cause.name();
+                    (name, deaths.iter().filter(|d| **d == name).count())
+                })
+                .collect()
+        };
+
+        Self {
+            tuning,
+            games,
+            mean_survival_time: mean(survival.clone()),
+            median_survival_time: survival.get(games / 2).copied().unwrap_or(0.0),
+            mean_wave: mean(stats.iter().map(|s| s.wave as f32).collect()),
+            max_wave: stats.iter().map(|s| s.wave).max().unwrap_or(0),
+            mean_score: mean(scores.iter().map(|s| *s as f32).collect()),
+            score_quartiles: [0.0, 0.25, 0.5, 0.75, 1.0].map(quantile),
+            deaths: count(stats.iter().flat_map(|s| s.deaths.clone()).collect()),
+            final_deaths: count(
+                stats
+                    .iter()
+                    .filter(|s| s.game_over)
+                    .filter_map(|s| s.deaths.last().copied())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rollback::tests::SIMULATION, SHIP_RESPAWN_DELAY};
+
+    #[test]
+    fn games_are_reproducible_and_summarised() {
+        let _lock = SIMULATION.lock().unwrap();
+        let tuning = Tuning {
+            saucer_spawn_rate: 2.0,
+            ..Default::default()
+        };
+        let stats: Vec<GameStats> = (0..3).map(|seed| simulate(tuning, seed, 120.0)).collect();
+        let again = simulate(tuning, 1, 120.0);
+        assert_eq!(
+            (again.score, again.wave, &again.deaths, again.survival_time),
+            (
+                stats[1].score,
+                stats[1].wave,
+                &stats[1].deaths,
+                stats[1].survival_time
+            )
+        );
+        // lost games stop the clock at the last hit, not once the explosion has finished
+        let lost = simulate(tuning, 1, f32::INFINITY);
+        assert!(lost.game_over);
+        let cut_short = simulate(tuning, 1, lost.survival_time + SHIP_RESPAWN_DELAY / 2.0);
+        assert!(!cut_short.game_over);
+        assert_eq!(cut_short.deaths, lost.deaths);
+
+        let summary = Summary::new(tuning, &stats);
+        let [min, _, median, _, max] = summary.score_quartiles;
+        assert!(min <= median && median <= max);
+        assert_eq!(max, stats.iter().map(|s| s.score).max().unwrap());
+        let deaths: usize = summary.deaths.iter().map(|(_, count)| count).sum();
+        assert_eq!(deaths, stats.iter().map(|s| s.deaths.len()).sum());
+    }
+}
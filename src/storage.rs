@@ -0,0 +1,65 @@
+//! Small text values kept between runs, in files on native builds and in the browser's local
+//! storage on the web
+
+/// Directory the values are kept in, ASTEROIDS_DATA_DIR if set, otherwise .asteroids in the home
+/// directory
+#[cfg(not(target_arch = "wasm32"))]
+fn data_dir() -> std::path::PathBuf {
+    use std::{env, path::PathBuf};
+
+    if let Ok(dir) = env::var("ASTEROIDS_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".asteroids"))
+        .unwrap_or_else(|_| PathBuf::from(".asteroids"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(data_dir().join(format!("{key}.txt"))).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(key: &str, value: &str) {
+    let dir = data_dir();
+    if let Err(e) = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(dir.join(format!("{key}.txt")), value))
+    {
+        eprintln!("can't save {key}: {e}");
+    }
+}
+
+// implemented by web/storage.js, which has to be loaded alongside the miniquad bundle
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    /// Copies the value into the buffer returning its full length, -1 if there isn't one
+    fn asteroids_storage_load(key: *const u8, key_len: usize, buffer: *mut u8, len: usize) -> i32;
+    fn asteroids_storage_save(key: *const u8, key_len: usize, value: *const u8, len: usize);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(key: &str) -> Option<String> {
+    let mut buffer = vec![0; 1024];
+    loop {
+        let len = unsafe {
+            asteroids_storage_load(key.as_ptr(), key.len(), buffer.as_mut_ptr(), buffer.len())
+        };
+        if len < 0 {
+            return None;
+        }
+        // go round again with room for all of it
+        if len as usize > buffer.len() {
+            buffer.resize(len as usize, 0);
+            continue;
+        }
+        buffer.truncate(len as usize);
+        return String::from_utf8(buffer).ok();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(key: &str, value: &str) {
+    unsafe { asteroids_storage_save(key.as_ptr(), key.len(), value.as_ptr(), value.len()) }
+}
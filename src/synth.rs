@@ -0,0 +1,260 @@
+//! Retro sound effects synthesised from oscillators and noise, rendered to sample buffers
+
+use std::f32::consts::{PI, TAU};
+
+use crate::{AsteroidType, SaucerSize};
+
+pub const SAMPLE_RATE: u32 = 22_050;
+
+/// Every sound the game makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sfx {
+    Thrust,
+    Laser,
+    Explosion(AsteroidType),
+    SaucerWarble(SaucerSize),
+    Hyperspace,
+    ExtraLife,
+    // the two alternating notes of the background heartbeat
+    BeatLow,
+    BeatHigh,
+}
+
+impl Sfx {
+    pub const ALL: [Sfx; 11] = [
+        Self::Thrust,
+        Self::Laser,
+        Self::Explosion(AsteroidType::Small),
+        Self::Explosion(AsteroidType::Medium),
+        Self::Explosion(AsteroidType::Large),
+        Self::SaucerWarble(SaucerSize::Large),
+        Self::SaucerWarble(SaucerSize::Small),
+        Self::Hyperspace,
+        Self::ExtraLife,
+        Self::BeatLow,
+        Self::BeatHigh,
+    ];
+
+    /// Whether it plays on repeat for as long as whatever makes it lasts
+    pub fn is_looped(&self) -> bool {
+        matches!(self, Self::Thrust | Self::SaucerWarble(_))
+    }
+
+    /// Whether it is turned up and down with the music rather than the effects
+    pub fn is_music(&self) -> bool {
+        matches!(self, Self::BeatLow | Self::BeatHigh)
+    }
+
+    /// How loud it plays relative to the others, every sound is rendered at full volume
+    pub fn volume(&self) -> f32 {
+        match self {
+            Self::Thrust => 0.5,
+            Self::Laser => 0.35,
+            Self::Explosion(_) => 0.8,
+            Self::SaucerWarble(_) => 0.25,
+            Self::Hyperspace => 0.6,
+            Self::ExtraLife => 0.7,
+            Self::BeatLow | Self::BeatHigh => 1.0,
+        }
+    }
+
+    /// Mono samples between -1 and 1 at SAMPLE_RATE
+    pub fn render(&self) -> Vec<f32> {
+        let mut noise = Noise(0x2545_f491);
+        let mut samples = match self {
+            // low rumble, played on a loop while thrusting
+            Self::Thrust => {
+                let mut filter = LowPass::default();
+                render(0.5, |_| filter.next(noise.next(), 180.0))
+            }
+            // square wave falling quickly in pitch
+            Self::Laser => {
+                let mut phase = 0.0;
+                render(0.15, |t| {
+                    phase += (1400.0 - 1200.0 * (t / 0.15)) / SAMPLE_RATE as f32;
+                    square(phase) * (1.0 - t / 0.15)
+                })
+            }
+            // bigger asteroids burst with a longer, deeper rumble
+            Self::Explosion(size) => {
+                let (length, cutoff) = match size {
+                    AsteroidType::Small => (0.3, 2400.0),
+                    AsteroidType::Medium => (0.5, 1000.0),
+                    AsteroidType::Large => (0.8, 400.0),
+                };
+                let mut filter = LowPass::default();
+                render(length, |t| {
+                    filter.next(noise.next(), cutoff) * (-5.0 * t / length).exp()
+                })
+            }
+            // siren whose pitch wobbles, the small saucer higher and more frantic
+            Self::SaucerWarble(size) => {
+                let (pitch, rate, depth) = match size {
+                    SaucerSize::Large => (220.0, 4.0, 40.0),
+                    SaucerSize::Small => (440.0, 8.0, 80.0),
+                };
+                // whole numbers of cycles in a second, so the loop joins up
+                render(1.0, |t| {
+                    let phase = pitch * t + depth / (TAU * rate) * (1.0 - (TAU * rate * t).cos());
+                    square(phase)
+                })
+            }
+            // noise swept up and back down
+            Self::Hyperspace => {
+                let mut filter = LowPass::default();
+                render(0.6, |t| {
+                    let sweep = (PI * t / 0.6).sin();
+                    filter.next(noise.next(), 200.0 + 3000.0 * sweep) * sweep
+                })
+            }
+            // rising arpeggio that rings on
+            Self::ExtraLife => render(0.6, |t| {
+                let notes = [1046.5, 1318.5, 1568.0, 2093.0];
+                let note = ((t / 0.08) as usize).min(notes.len() - 1);
+                let since = t - note as f32 * 0.08;
+                (TAU * notes[note] * t).sin() * (-6.0 * since).exp()
+            }),
+            // short muffled thump, sagging in pitch as it fades
+            Self::BeatLow | Self::BeatHigh => {
+                let pitch = if *self == Self::BeatLow { 55.0 } else { 65.0 };
+                let mut filter = LowPass::default();
+                let mut phase = 0.0;
+                render(0.15, |t| {
+                    phase += pitch * (1.0 - t) / SAMPLE_RATE as f32;
+                    filter.next(square(phase), 400.0) * (-20.0 * t).exp()
+                })
+            }
+        };
+        normalise(&mut samples);
+        samples
+    }
+}
+
+/// Samples for the given number of seconds, from a function of the time
+fn render(seconds: f32, mut sample: impl FnMut(f32) -> f32) -> Vec<f32> {
+    let count = (seconds * SAMPLE_RATE as f32) as usize;
+    (0..count)
+        .map(|i| sample(i as f32 / SAMPLE_RATE as f32))
+        .collect()
+}
+
+/// Scales the loudest sample to full volume
+fn normalise(samples: &mut [f32]) {
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak > 0.0 {
+        samples.iter_mut().for_each(|s| *s /= peak);
+    }
+}
+
+fn square(phase: f32) -> f32 {
+    if phase.fract() < 0.5 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+/// White noise from a generator of its own, so sounds come out the same every time
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+/// One pole low pass filter, for turning hiss into rumble
+#[derive(Default)]
+struct LowPass(f32);
+
+impl LowPass {
+    fn next(&mut self, input: f32, cutoff: f32) -> f32 {
+        let alpha = 1.0 - (-TAU * cutoff / SAMPLE_RATE as f32).exp();
+        self.0 += alpha * (input - self.0);
+        self.0
+    }
+}
+
+/// The samples as a 16 bit WAV file, interleaved when there is more than one channel
+pub fn wav(samples: &[f32], channels: u16) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // uncompressed
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2 * channels as u32).to_le_bytes());
+    wav.extend_from_slice(&(2 * channels).to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rough pitch, from how often the signal crosses zero
+    fn crossings_per_second(samples: &[f32]) -> f32 {
+        let crossings = samples
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count();
+        crossings as f32 * SAMPLE_RATE as f32 / samples.len() as f32
+    }
+
+    #[test]
+    fn every_sound_renders_within_range() {
+        for sfx in Sfx::ALL {
+            let samples = sfx.render();
+            assert!(!samples.is_empty(), "{sfx:?} is empty");
+            assert!(samples.iter().all(|s| s.is_finite() && s.abs() <= 1.0));
+            assert!(
+                samples.iter().any(|s| s.abs() > 0.99),
+                "{sfx:?} isn't normalised"
+            );
+            assert_eq!(
+                samples,
+                sfx.render(),
+                "{sfx:?} should render the same each time"
+            );
+        }
+    }
+
+    #[test]
+    fn bigger_things_sound_lower_and_last_longer() {
+        let small = Sfx::Explosion(AsteroidType::Small).render();
+        let large = Sfx::Explosion(AsteroidType::Large).render();
+        assert!(large.len() > small.len());
+        assert!(crossings_per_second(&large) < crossings_per_second(&small));
+
+        let large = Sfx::SaucerWarble(SaucerSize::Large).render();
+        let small = Sfx::SaucerWarble(SaucerSize::Small).render();
+        assert!(crossings_per_second(&large) < crossings_per_second(&small));
+
+        let low = Sfx::BeatLow.render();
+        let high = Sfx::BeatHigh.render();
+        assert!(crossings_per_second(&low) < crossings_per_second(&high));
+    }
+
+    #[test]
+    fn wav_has_a_header_and_sixteen_bit_samples() {
+        let wav = wav(&[0.0, 1.0, -1.0], 1);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(wav.len(), 44 + 3 * 2);
+        assert_eq!(i16::from_le_bytes([wav[46], wav[47]]), i16::MAX);
+        assert_eq!(i16::from_le_bytes([wav[48], wav[49]]), -i16::MAX);
+    }
+}
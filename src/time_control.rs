@@ -0,0 +1,158 @@
+//! Debug controls over how a local game advances: pausing, stepping a tick at a time, slow
+//! motion and rewinding through recent snapshots
+
+use std::collections::VecDeque;
+
+use macroquad::{prelude::*, rand};
+
+use crate::{render::Frame, Game, TIME_REWIND_SNAPSHOTS, TIME_SLOW_MOTION, TIME_STEP};
+
+pub struct TimeControl {
+    pub paused: bool,
+    /// How fast play runs, below one for slow motion
+    pub scale: f32,
+    // the game before each recent update and the seed it was run with, oldest first
+    snapshots: VecDeque<(Game, u64)>,
+    // seed of the update rewound to, so running on from it plays out as it did the first time
+    replay_seed: Option<u64>,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            scale: 1.0,
+            snapshots: VecDeque::new(),
+            replay_seed: None,
+        }
+    }
+}
+
+impl TimeControl {
+    /// F5 pauses, F6 steps a tick, F7 cycles through slow motion and holding F8 rewinds.
+    /// Returns how far to run the game this frame, none to leave it where it is. Online games
+    /// aren't the only one playing them, so they always run as normal
+    pub fn update(&mut self, game: Option<&mut Game>, frame_time: f32) -> Option<f32> {
+        let Some(game) = game else {
+            return Some(frame_time);
+        };
+
+        if is_key_pressed(KeyCode::F5) {
+            self.paused = !self.paused;
+        }
+        if is_key_pressed(KeyCode::F7) {
+            let next = TIME_SLOW_MOTION
+                .iter()
+                .position(|s| *s == self.scale)
+                .map_or(0, |i| (i + 1) % TIME_SLOW_MOTION.len());
+            self.scale = TIME_SLOW_MOTION[next];
+        }
+        if is_key_down(KeyCode::F8) {
+            self.rewind(game);
+            return None;
+        }
+        let step = is_key_pressed(KeyCode::F6);
+        if step {
+            self.paused = true;
+        }
+        self.advance(game, frame_time, step)
+    }
+
+    /// Snapshots the game before it is run on, for as far as it has to go
+    fn advance(&mut self, game: &Game, frame_time: f32, step: bool) -> Option<f32> {
+        if self.paused && !step {
+            return None;
+        }
+
+        // snapshots of another game are no use, like after a restart or on the other player's turn
+        if self
+            .snapshots
+            .back()
+            .is_some_and(|(snapshot, _)| snapshot.frame + 1 != game.frame)
+        {
+            self.snapshots.clear();
+        }
+        // there's no reading back the random number generator, so start it from a known seed
+        let seed = self
+            .replay_seed
+            .take()
+            .unwrap_or_else(|| rand::rand() as u64);
+        rand::srand(seed);
+        self.snapshots.push_back((game.clone(), seed));
+        if self.snapshots.len() > TIME_REWIND_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+
+        Some(if step {
+            TIME_STEP
+        } else {
+            frame_time * self.scale
+        })
+    }
+
+    /// Goes back an update, pausing so play can be stepped on from there. Whatever is earned in
+    /// the game from then on could be earned over and over, so it no longer counts
+    fn rewind(&mut self, game: &mut Game) {
+        self.paused = true;
+        if let Some((snapshot, seed)) = self.snapshots.pop_back() {
+            *game = snapshot;
+            self.replay_seed = Some(seed);
+        }
+        game.cheated = true;
+    }
+
+    /// What is being done to time, in the bottom left corner
+    pub fn frame(&self, screen: Vec2) -> Frame {
+        let mut frame = Frame::default();
+        let state = if self.paused {
+            format!("PAUSED  {} updates to rewind", self.snapshots.len())
+        } else if self.scale != 1.0 {
+            format!("{}x", self.scale)
+        } else {
+            return frame;
+        };
+        frame.text(&state, Vec2::new(10.0, screen.y - 60.0), 24.0, YELLOW);
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pilot::pilot, rollback::checksum, rollback::tests::SIMULATION, ShipInput};
+
+    #[test]
+    fn rewound_play_runs_the_same_again() {
+        let _lock = SIMULATION.lock().unwrap();
+        rand::srand(7);
+        let mut time = TimeControl::default();
+        let mut game = Game::new(1, Vec2::new(800.0, 600.0));
+
+        let run = |time: &mut TimeControl, game: &mut Game, inputs: &mut Vec<ShipInput>| {
+            for tick in 0..120 {
+                let delta_t = time.advance(game, 1.0 / 60.0, false).unwrap();
+                let input = *inputs.get(tick).unwrap_or(&pilot(game, 0));
+                inputs.push(input);
+                game.update(delta_t, &[input]);
+            }
+        };
+        let mut inputs = Vec::new();
+        run(&mut time, &mut game, &mut inputs);
+        let ahead = checksum(&game);
+
+        for _ in 0..120 {
+            time.rewind(&mut game);
+        }
+        assert!(time.paused && game.cheated);
+        assert_eq!(game.frame, 0);
+
+        time.paused = false;
+        run(&mut time, &mut game, &mut inputs);
+        assert_eq!(checksum(&game), ahead);
+
+        // while paused nothing happens unless stepped
+        time.paused = true;
+        assert_eq!(time.advance(&game, 1.0, false), None);
+        assert_eq!(time.advance(&game, 1.0, true), Some(TIME_STEP));
+    }
+}
@@ -0,0 +1,48 @@
+// WebSocket transport for online play, load after mq_js_bundle.js and before load("asteroids-wasm.wasm")
+(function () {
+    var sockets = [];
+
+    function register_plugin(importObject) {
+        importObject.env.asteroids_net_connect = function (ptr, len) {
+            var url = new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+            var socket = { ws: new WebSocket(url), outgoing: [], incoming: [], closed: false };
+            socket.ws.binaryType = "arraybuffer";
+            socket.ws.onopen = function () {
+                socket.outgoing.forEach(function (data) { socket.ws.send(data); });
+                socket.outgoing = [];
+            };
+            socket.ws.onmessage = function (event) {
+                socket.incoming.push(new Uint8Array(event.data));
+            };
+            socket.ws.onclose = socket.ws.onerror = function () {
+                socket.closed = true;
+            };
+            sockets.push(socket);
+            return sockets.length - 1;
+        };
+
+        importObject.env.asteroids_net_send = function (id, ptr, len) {
+            var socket = sockets[id];
+            var data = new Uint8Array(wasm_memory.buffer, ptr, len).slice();
+            if (socket.ws.readyState === WebSocket.OPEN) {
+                socket.ws.send(data);
+            } else {
+                // messages sent while connecting go out once the socket opens
+                socket.outgoing.push(data);
+            }
+        };
+
+        importObject.env.asteroids_net_receive = function (id, ptr, len) {
+            var socket = sockets[id];
+            if (socket.incoming.length === 0) {
+                return socket.closed ? -1 : 0;
+            }
+            var data = socket.incoming.shift();
+            var size = Math.min(data.length, len);
+            new Uint8Array(wasm_memory.buffer, ptr, size).set(data.subarray(0, size));
+            return size;
+        };
+    }
+
+    miniquad_add_plugin({ register_plugin: register_plugin, name: "asteroids_net", version: 1 });
+})();
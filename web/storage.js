@@ -0,0 +1,27 @@
+// Local storage for achievements and settings, load after mq_js_bundle.js and before load("asteroids-wasm.wasm")
+(function () {
+    function read(ptr, len) {
+        return new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+    }
+
+    function register_plugin(importObject) {
+        importObject.env.asteroids_storage_load = function (key_ptr, key_len, ptr, len) {
+            var value = window.localStorage.getItem("asteroids." + read(key_ptr, key_len));
+            if (value === null) {
+                return -1;
+            }
+            var data = new TextEncoder().encode(value);
+            // too long for the buffer, the caller asks again with a bigger one
+            if (data.length <= len) {
+                new Uint8Array(wasm_memory.buffer, ptr, data.length).set(data);
+            }
+            return data.length;
+        };
+
+        importObject.env.asteroids_storage_save = function (key_ptr, key_len, ptr, len) {
+            window.localStorage.setItem("asteroids." + read(key_ptr, key_len), read(ptr, len));
+        };
+    }
+
+    miniquad_add_plugin({ register_plugin: register_plugin, name: "asteroids_storage", version: 1 });
+})();